@@ -0,0 +1,122 @@
+//! Time budgets and cancellation for long-running solvers.
+//!
+//! Solvers which may run for a very long time on pathological inputs accept a
+//! [`Budget`] and call [`Budget::check`] in their inner loops. Once the budget
+//! is exhausted they return [`TimedOut`] with the answer accumulated so far.
+
+use std::{
+    cell::Cell,
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Number of calls to [`Budget::check`] between two reads of the clock.
+const CHECK_INTERVAL: u32 = 1024;
+
+/// Limits the time a solver may run for.
+///
+/// Clones of a budget share the same cancellation flag, so a clone may be
+/// moved to another thread to cancel a running solver.
+#[derive(Clone, Debug)]
+pub struct Budget {
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+    calls: Cell<u32>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Self {
+        Budget {
+            deadline: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            calls: Cell::new(0),
+        }
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Budget {
+            deadline: Instant::now().checked_add(timeout),
+            ..Self::unlimited()
+        }
+    }
+
+    /// Makes every solver using this budget (or a clone of it) stop as soon
+    /// as possible.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Cheap check meant to be called in inner loops, the clock is only read
+    /// every [`CHECK_INTERVAL`] calls.
+    pub fn check(&self) -> Result<(), Exhausted> {
+        let calls = self.calls.get().wrapping_add(1);
+        self.calls.set(calls);
+        if calls.is_multiple_of(CHECK_INTERVAL) && self.is_exhausted() {
+            Err(Exhausted)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returned by [`Budget::check`] once the budget is exhausted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exhausted;
+
+/// Returned by a solver which ran out of budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedOut {
+    /// Answer over the part of the input which was processed in time.
+    pub partial_answer: String,
+    /// Describes how much of the work was completed.
+    pub progress: String,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "timed out after {} (partial answer: {})",
+            self.progress, self.partial_answer
+        )
+    }
+}
+
+impl Error for TimedOut {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_budget_is_never_exhausted() {
+        let budget = Budget::unlimited();
+        assert!((0..10 * CHECK_INTERVAL).all(|_| budget.check().is_ok()));
+    }
+
+    #[test]
+    fn cancel_exhausts_clones() {
+        let budget = Budget::unlimited();
+        budget.clone().cancel();
+        assert!(budget.is_exhausted());
+        assert!((0..CHECK_INTERVAL).any(|_| budget.check().is_err()));
+    }
+
+    #[test]
+    fn zero_timeout_is_exhausted() {
+        let budget = Budget::with_timeout(Duration::ZERO);
+        assert!(budget.is_exhausted());
+    }
+}
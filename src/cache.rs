@@ -0,0 +1,321 @@
+//! Binary cache of parsed puzzle inputs.
+//!
+//! Parsed inputs are serialized with [`Codec`] into `<dir>/day<N>-<hash>.bin`,
+//! where the hash covers the raw input and [`FORMAT_VERSION`]. Integers are
+//! stored as LEB128 varints, so the cached form is usually smaller than the
+//! puzzle input itself.
+
+use std::{
+    collections::HashMap,
+    fs,
+    hash::Hash,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::error::ParseError;
+
+/// Must be bumped whenever the encoding of any parsed input type changes, so
+/// that stale cache entries are no longer found.
+const FORMAT_VERSION: u32 = 3;
+
+/// FNV-1a, a simple hash which is stable across Rust versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Compact binary serialization of parsed inputs.
+pub(crate) trait Codec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    /// Decodes a value from the front of `input`, advancing it. Returns `None`
+    /// if `input` is truncated or corrupt.
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+impl Codec for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut v = *self;
+        while v >= 0x80 {
+            out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let mut v = 0;
+        for shift in (0..64).step_by(7) {
+            let (b, rest) = input.split_first()?;
+            *input = rest;
+            if shift == 63 && *b > 1 {
+                // only the lowest bit of the 10th byte fits into a u64
+                return None;
+            }
+            v |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Some(v);
+            }
+        }
+        None
+    }
+}
+
+impl Codec for i64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // zigzag encoding keeps small negative numbers small
+        (((*self << 1) ^ (*self >> 63)) as u64).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let v = u64::decode(input)?;
+        Some((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+}
+
+macro_rules! impl_codec_via {
+    ($t:ty, $via:ty) => {
+        impl Codec for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                <$via>::try_from(*self)
+                    .expect("lossless conversion")
+                    .encode(out);
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                <$via>::decode(input).and_then(|v| Self::try_from(v).ok())
+            }
+        }
+    };
+}
+
+impl_codec_via!(u8, u64);
+impl_codec_via!(u32, u64);
+impl_codec_via!(usize, u64);
+impl_codec_via!(isize, i64);
+
+impl Codec for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::from(*self).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        char::from_u32(u32::decode(input)?)
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        self.iter().for_each(|v| v.encode(out));
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl Codec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        let bytes = input.get(..len)?;
+        *input = &input[len..];
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl<K: Codec + Eq + Hash, V: Codec> Codec for HashMap<K, V> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        self.iter().for_each(|(k, v)| {
+            k.encode(out);
+            v.encode(out);
+        });
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        (0..len)
+            .map(|_| Some((K::decode(input)?, V::decode(input)?)))
+            .collect()
+    }
+}
+
+/// Encodes `value` into a new buffer.
+pub(crate) fn to_bytes<T: Codec>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+/// Decodes a value which must span all of `bytes`.
+pub(crate) fn from_bytes<T: Codec>(mut bytes: &[u8]) -> Option<T> {
+    let value = T::decode(&mut bytes)?;
+    bytes.is_empty().then_some(value)
+}
+
+/// Directory holding the cached parsed inputs.
+pub(crate) struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub(crate) fn new(dir: impl AsRef<Path>) -> Self {
+        Cache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn file_prefix(day: u32) -> String {
+        format!("day{}-", day)
+    }
+
+    fn path(&self, day: u32, input: &str, variant: &str) -> PathBuf {
+        let mut key = FORMAT_VERSION.to_le_bytes().to_vec();
+        key.extend_from_slice(variant.as_bytes());
+        key.push(0);
+        key.extend_from_slice(input.as_bytes());
+        self.dir.join(format!(
+            "{}{:016x}.bin",
+            Self::file_prefix(day),
+            fnv1a(&key)
+        ))
+    }
+
+    /// Removes all cached inputs of `day`.
+    pub(crate) fn invalidate(&self, day: u32) -> io::Result<()> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&Self::file_prefix(day))
+            {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the cached parsed `input` of `day`, or parses and caches it.
+    /// `variant` distinguishes results of differently configured parsers.
+    /// Failing to write the cache is not an error, the input is parsed anyway.
+    pub(crate) fn load_or_parse<T: Codec>(
+        &self,
+        day: u32,
+        input: &str,
+        variant: &str,
+        parse: impl FnOnce(&str) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let path = self.path(day, input, variant);
+        if let Some(value) = fs::read(&path).ok().and_then(|b| from_bytes(&b)) {
+            return Ok(value);
+        }
+
+        let value = parse(input)?;
+        if let Err(e) =
+            fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, to_bytes(&value)))
+        {
+            eprintln!("warning: failed to cache {}: {}", path.display(), e);
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip<T: Codec + PartialEq + std::fmt::Debug>(value: T) {
+        assert_eq!(from_bytes::<T>(&to_bytes(&value)), Some(value));
+    }
+
+    #[test]
+    fn roundtrip_integers() {
+        [0, 1, 127, 128, 300, u64::MAX]
+            .into_iter()
+            .for_each(roundtrip);
+        [0, -1, 1, -64, 64, i64::MIN, i64::MAX]
+            .into_iter()
+            .for_each(roundtrip);
+        roundtrip(u32::MAX);
+        roundtrip(isize::MIN);
+        roundtrip('ß');
+    }
+
+    #[test]
+    fn roundtrip_collections() {
+        roundtrip(vec![vec![1u32, 2], vec![], vec![u32::MAX]]);
+        roundtrip((String::from("mul(2,4)"), vec![-1i64, 5]));
+        roundtrip(HashMap::from([
+            ('A', vec![(0usize, 1usize)]),
+            ('0', vec![]),
+        ]));
+    }
+
+    #[test]
+    fn small_integers_are_compact() {
+        assert_eq!(to_bytes(&vec![1u32, 2, 3]), [3, 1, 2, 3]);
+        assert_eq!(to_bytes(&-1i64), [1]);
+    }
+
+    #[test]
+    fn reject_corrupt_bytes() {
+        let bytes = to_bytes(&vec![1000u32, 2000]);
+        assert_eq!(from_bytes::<Vec<u32>>(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(
+            from_bytes::<Vec<u32>>(&[bytes.as_slice(), &[0]].concat()),
+            None
+        );
+        assert_eq!(from_bytes::<u32>(&to_bytes(&u64::MAX)), None);
+        assert_eq!(from_bytes::<char>(&to_bytes(&0xd800u32)), None);
+
+        let mut overlong = to_bytes(&u64::MAX);
+        assert_eq!(overlong.len(), 10);
+        *overlong.last_mut().unwrap() = 0x03;
+        assert_eq!(from_bytes::<u64>(&overlong), None);
+        *overlong.last_mut().unwrap() = 0x81;
+        assert_eq!(from_bytes::<u64>(&overlong), None);
+    }
+
+    #[test]
+    fn load_or_parse_uses_cache() {
+        let dir = std::env::temp_dir().join(format!("aoc-2024-cache-{}", std::process::id()));
+        let cache = Cache::new(&dir);
+        let parse = |input: &str| Ok(input.len());
+        let fail = |_: &str| Err::<usize, _>(ParseError::input("not cached"));
+
+        assert_eq!(cache.load_or_parse(1, "abc", "", parse), Ok(3));
+        assert_eq!(cache.load_or_parse(1, "abc", "", fail), Ok(3));
+        assert!(cache.load_or_parse(1, "abcd", "", fail).is_err());
+        assert!(cache.load_or_parse(1, "abc", "variant", fail).is_err());
+        assert!(cache.load_or_parse(2, "abc", "", fail).is_err());
+
+        cache.invalidate(1).unwrap();
+        assert!(cache.load_or_parse(1, "abc", "", fail).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+//! Command line interface to run the solutions outside of cargo-aoc.
+
+use std::{env, error::Error, fs, path::Path, process::ExitCode, time::Duration};
+
+use crate::{
+    budget::{Budget, TimedOut},
+    cache::{Cache, Codec},
+    client::Client,
+    day1, day2, day3, day4, day5, day6, day7, day8, day9,
+    error::ParseError,
+};
+
+const USAGE: &str = "\
+Usage: aoc-2024 <command> <day> [options]
+
+Commands:
+    run                 solve both parts of the given day
+    describe            summarize the shape of the puzzle input
+    explain             explain how the answers are computed
+    fetch               download the puzzle input
+    submit              solve the part given by --part and submit the answer
+
+Options:
+    --part <1|2>        only run the given part
+    --input <path>      read the puzzle input from <path>
+                        (default: input/2024/day<day>.txt)
+    --timeout <secs>    stop long-running solvers after <secs> seconds
+    --invalidate-cache  parse the input again instead of using the cached
+                        result from a previous run
+    --length-policy <error|truncate|pad>
+                        day 1: how to deal with lists of unequal length
+
+Environment:
+    AOC_SESSION         session cookie used by fetch and submit
+    AOC_BASE_URL        server used by fetch and submit
+                        (default: https://adventofcode.com)";
+
+/// Where parsed inputs are cached between runs.
+const CACHE_DIR: &str = "target/aoc-cache";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Run,
+    Describe,
+    Explain,
+    Fetch,
+    Submit,
+}
+
+struct Options {
+    command: Command,
+    day: u32,
+    part: Option<u32>,
+    input_path: String,
+    timeout: Option<Duration>,
+    invalidate_cache: bool,
+    length_policy: day1::LengthPolicy,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let command = match args.next().as_deref() {
+            Some("run") => Command::Run,
+            Some("describe") => Command::Describe,
+            Some("explain") => Command::Explain,
+            Some("fetch") => Command::Fetch,
+            Some("submit") => Command::Submit,
+            Some(command) => return Err(format!("unknown command '{}'", command)),
+            None => return Err("missing command".to_string()),
+        };
+
+        let day = args.next().ok_or("missing day")?;
+        let day = day
+            .trim_start_matches("day")
+            .parse()
+            .map_err(|_| format!("invalid day '{}'", day))?;
+        let mut options = Options {
+            command,
+            day,
+            part: None,
+            input_path: format!("input/2024/day{}.txt", day),
+            timeout: None,
+            invalidate_cache: false,
+            length_policy: day1::LengthPolicy::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--part" => {
+                    let part = value()?;
+                    options.part = match part.as_str() {
+                        "1" => Some(1),
+                        "2" => Some(2),
+                        _ => return Err(format!("invalid part '{}'", part)),
+                    };
+                }
+                "--input" => options.input_path = value()?,
+                "--timeout" => {
+                    let secs = value()?;
+                    let timeout = secs
+                        .parse()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                        .ok_or(format!("invalid timeout '{}'", secs))?;
+                    options.timeout = Some(timeout);
+                }
+                "--invalidate-cache" => options.invalidate_cache = true,
+                "--length-policy" => options.length_policy = value()?.parse()?,
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+
+        if options.command == Command::Submit && options.part.is_none() {
+            return Err("submit requires --part".to_string());
+        }
+        Ok(options)
+    }
+
+    /// Options affecting the parsed input, which are part of the cache key.
+    fn parse_options_key(&self) -> String {
+        format!("length-policy={}", self.length_policy)
+    }
+
+    fn budget(&self) -> Budget {
+        self.timeout
+            .map_or_else(Budget::unlimited, Budget::with_timeout)
+    }
+}
+
+pub fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+type Solver<I> = fn(&I, &Budget) -> Result<String, TimedOut>;
+
+/// Everything the command line interface needs to know about a day.
+struct Day<I> {
+    parse: fn(&str, &Options) -> Result<I, ParseError>,
+    solvers: [Solver<I>; 2],
+    describe: fn(&I) -> String,
+    explain: Option<fn(&I) -> String>,
+}
+
+fn client() -> Result<Client, Box<dyn Error>> {
+    let session = env::var("AOC_SESSION").map_err(|_| "AOC_SESSION is not set")?;
+    Ok(match env::var("AOC_BASE_URL") {
+        Ok(base_url) => Client::with_base_url(base_url, session),
+        Err(_) => Client::new(session),
+    })
+}
+
+fn fetch(options: &Options) -> Result<(), Box<dyn Error>> {
+    let input = client()?.fetch_input(options.day)?;
+    if let Some(dir) = Path::new(&options.input_path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&options.input_path, input)?;
+    println!(
+        "Saved input of day {} to {}",
+        options.day, options.input_path
+    );
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    if options.command == Command::Fetch {
+        return fetch(options);
+    }
+
+    let input = fs::read_to_string(&options.input_path)
+        .map_err(|e| format!("failed to read {}: {}", options.input_path, e))?;
+    // cargo-aoc strips trailing newlines as well
+    let input = input.trim_end_matches('\n');
+
+    match options.day {
+        1 => Day {
+            parse: |i, options| day1::parse_with_policy(i, options.length_policy),
+            solvers: [|i, _| Ok(day1::part1(i)), |i, _| Ok(day1::part2(i))],
+            describe: day1::describe,
+            explain: Some(day1::explain),
+        }
+        .execute(input, options),
+        2 => Day {
+            parse: |i, _| day2::parse(i),
+            solvers: [|i, _| Ok(day2::part1(i)), |i, _| Ok(day2::part2(i))],
+            describe: day2::describe,
+            explain: Some(day2::explain),
+        }
+        .execute(input, options),
+        3 => Day {
+            parse: |i, _| Ok(day3::parse(i)),
+            solvers: [|i, _| Ok(day3::part1(i)), |i, _| Ok(day3::part2(i))],
+            describe: |i| day3::describe(i),
+            explain: Some(|i| day3::explain(i)),
+        }
+        .execute(input, options),
+        4 => Day {
+            parse: |i, _| Ok(day4::parse(i)),
+            solvers: [|i, _| Ok(day4::part1(i)), |i, _| Ok(day4::part2(i))],
+            describe: |i| day4::describe(i),
+            explain: None,
+        }
+        .execute(input, options),
+        5 => Day {
+            parse: |i, _| day5::parse(i),
+            solvers: [|i, _| Ok(day5::part1(i)), |i, _| Ok(day5::part2(i))],
+            describe: day5::describe,
+            explain: None,
+        }
+        .execute(input, options),
+        6 => Day {
+            parse: |i, _| day6::parse(i),
+            solvers: [|i, _| Ok(day6::part1(i)), day6::part2_with_budget],
+            describe: day6::describe,
+            explain: None,
+        }
+        .execute(input, options),
+        7 => Day {
+            parse: |i, _| day7::parse(i),
+            solvers: [
+                |i, budget| day7::part1_with_budget(i, budget),
+                |i, budget| day7::part2_with_budget(i, budget),
+            ],
+            describe: |i| day7::describe(i),
+            explain: None,
+        }
+        .execute(input, options),
+        8 => Day {
+            parse: |i, _| Ok(day8::parse(i)),
+            solvers: [|i, _| Ok(day8::part1(i)), |i, _| Ok(day8::part2(i))],
+            describe: day8::describe,
+            explain: None,
+        }
+        .execute(input, options),
+        9 => Day {
+            parse: |i, _| day9::parse(i),
+            solvers: [|i, _| Ok(day9::part1(i)), |i, _| Ok(day9::part2(i))],
+            describe: day9::describe,
+            explain: None,
+        }
+        .execute(input, options),
+        day => Err(format!("no solution for day {}", day).into()),
+    }
+}
+
+impl<I: Codec> Day<I> {
+    fn execute(&self, input: &str, options: &Options) -> Result<(), Box<dyn Error>> {
+        let cache = Cache::new(CACHE_DIR);
+        if options.invalidate_cache {
+            cache.invalidate(options.day)?;
+        }
+        let input =
+            cache.load_or_parse(options.day, input, &options.parse_options_key(), |input| {
+                (self.parse)(input, options)
+            })?;
+        match options.command {
+            Command::Run => self.solve(&input, options),
+            Command::Describe => {
+                println!("{}", (self.describe)(&input));
+                Ok(())
+            }
+            Command::Explain => {
+                let explain = self
+                    .explain
+                    .ok_or(format!("no explanation for day {}", options.day))?;
+                println!("{}", explain(&input));
+                Ok(())
+            }
+            Command::Submit => {
+                let part = options.part.expect("checked by Options::parse");
+                let answer = self.solvers[part as usize - 1](&input, &options.budget())?;
+                let verdict = client()?.submit_answer(options.day, part, &answer)?;
+                println!(
+                    "Day {} - Part {}: {} is {}",
+                    options.day, part, answer, verdict
+                );
+                Ok(())
+            }
+            Command::Fetch => unreachable!("fetch does not need a parsed input"),
+        }
+    }
+
+    fn solve(&self, input: &I, options: &Options) -> Result<(), Box<dyn Error>> {
+        let mut timed_out = false;
+        for (part, solver) in (1..).zip(self.solvers) {
+            if options.part.is_some_and(|p| p != part) {
+                continue;
+            }
+            match solver(input, &options.budget()) {
+                Ok(answer) => println!("Day {} - Part {}: {}", options.day, part, answer),
+                Err(e) => {
+                    println!("Day {} - Part {}: {}", options.day, part, e);
+                    timed_out = true;
+                }
+            }
+        }
+
+        if timed_out {
+            Err("not all parts finished in time".into())
+        } else {
+            Ok(())
+        }
+    }
+}
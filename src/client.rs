@@ -0,0 +1,250 @@
+//! Client for downloading puzzle inputs from and submitting answers to
+//! Advent of Code.
+
+use std::{error::Error, fmt, io, time::Duration};
+
+const BASE_URL: &str = "https://adventofcode.com";
+const YEAR: u32 = 2024;
+const USER_AGENT: &str = "aoc-2024 (+https://github.com/nhaef/AoC-2024)";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server did not respond with 200 OK, e.g. because the session token
+    /// is invalid or the puzzle is not unlocked yet.
+    Status(u16, String),
+    Transport(Box<ureq::Transport>),
+    Io(io::Error),
+    /// The server responded to an answer with a message we did not recognize.
+    UnexpectedResponse(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Status(status, body) => {
+                write!(f, "server responded with {}: {}", status, body.trim())
+            }
+            ClientError::Transport(e) => write!(f, "{}", e),
+            ClientError::Io(e) => write!(f, "failed to read response: {}", e),
+            ClientError::UnexpectedResponse(body) => {
+                write!(f, "unexpected response: {}", body.trim())
+            }
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+impl From<ureq::Error> for ClientError {
+    fn from(e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::Status(status, response) => {
+                ClientError::Status(status, response.into_string().unwrap_or_default())
+            }
+            ureq::Error::Transport(transport) => ClientError::Transport(Box::new(transport)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hint {
+    TooHigh,
+    TooLow,
+}
+
+/// The server's verdict on a submitted answer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    Incorrect(Option<Hint>),
+    /// Answers are rate limited after a wrong answer. `wait` is the remaining
+    /// time as reported by the server, e.g. `"42s"`.
+    TooSoon {
+        wait: Option<String>,
+    },
+    /// The part was already solved or is not unlocked yet.
+    WrongLevel,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::Incorrect(None) => write!(f, "incorrect"),
+            Verdict::Incorrect(Some(Hint::TooHigh)) => write!(f, "incorrect, too high"),
+            Verdict::Incorrect(Some(Hint::TooLow)) => write!(f, "incorrect, too low"),
+            Verdict::TooSoon { wait: Some(wait) } => {
+                write!(f, "answered too recently, wait {}", wait)
+            }
+            Verdict::TooSoon { wait: None } => write!(f, "answered too recently"),
+            Verdict::WrongLevel => write!(f, "part already solved or not unlocked yet"),
+        }
+    }
+}
+
+impl Verdict {
+    /// Classifies the HTML page the server responds with after submitting an
+    /// answer.
+    fn from_response(body: &str) -> Result<Self, ClientError> {
+        let article = body
+            .split_once("<article>")
+            .and_then(|(_, rest)| rest.split_once("</article>"))
+            .map_or(body, |(article, _)| article);
+
+        if article.contains("That's the right answer") {
+            Ok(Verdict::Correct)
+        } else if article.contains("That's not the right answer") {
+            let hint = if article.contains("too high") {
+                Some(Hint::TooHigh)
+            } else if article.contains("too low") {
+                Some(Hint::TooLow)
+            } else {
+                None
+            };
+            Ok(Verdict::Incorrect(hint))
+        } else if article.contains("You gave an answer too recently") {
+            let wait = article
+                .split_once("You have ")
+                .and_then(|(_, rest)| rest.split_once(" left to wait"))
+                .map(|(wait, _)| wait.to_string());
+            Ok(Verdict::TooSoon { wait })
+        } else if article.contains("You don't seem to be solving the right level") {
+            Ok(Verdict::WrongLevel)
+        } else {
+            Err(ClientError::UnexpectedResponse(article.to_string()))
+        }
+    }
+}
+
+pub struct Client {
+    base_url: String,
+    session: String,
+    agent: ureq::Agent,
+}
+
+impl Client {
+    /// Creates a client for adventofcode.com. `session` is the value of the
+    /// `session` cookie of a logged in browser.
+    pub fn new(session: impl Into<String>) -> Self {
+        Self::with_base_url(BASE_URL, session)
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>, session: impl Into<String>) -> Self {
+        Client {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            session: session.into(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(TIMEOUT)
+                .user_agent(USER_AGENT)
+                .build(),
+        }
+    }
+
+    fn url(&self, day: u32, path: &str) -> String {
+        format!("{}/{}/day/{}/{}", self.base_url, YEAR, day, path)
+    }
+
+    fn cookie(&self) -> String {
+        format!("session={}", self.session)
+    }
+
+    pub fn fetch_input(&self, day: u32) -> Result<String, ClientError> {
+        self.agent
+            .get(&self.url(day, "input"))
+            .set("Cookie", &self.cookie())
+            .call()?
+            .into_string()
+            .map_err(ClientError::Io)
+    }
+
+    pub fn submit_answer(&self, day: u32, part: u32, answer: &str) -> Result<Verdict, ClientError> {
+        let body = self
+            .agent
+            .post(&self.url(day, "answer"))
+            .set("Cookie", &self.cookie())
+            .send_form(&[("level", &part.to_string()), ("answer", answer)])?
+            .into_string()
+            .map_err(ClientError::Io)?;
+        Verdict::from_response(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::MockServer;
+
+    const SESSION: &str = "53616c7465645f5f";
+
+    fn server() -> MockServer {
+        MockServer::start(SESSION)
+            .with_input(1, "3   4\n4   3\n")
+            .with_answer(1, 1, "11")
+            .with_answer(1, 2, "31")
+    }
+
+    #[test]
+    fn fetch_input() {
+        let server = server();
+        let client = Client::with_base_url(server.base_url(), SESSION);
+        assert_eq!(client.fetch_input(1).unwrap(), "3   4\n4   3\n");
+    }
+
+    #[test]
+    fn fetch_input_with_invalid_session() {
+        let server = server();
+        let client = Client::with_base_url(server.base_url(), "invalid");
+        assert!(matches!(
+            client.fetch_input(1),
+            Err(ClientError::Status(400, _))
+        ));
+    }
+
+    #[test]
+    fn fetch_input_of_locked_day() {
+        let server = server();
+        let client = Client::with_base_url(server.base_url(), SESSION);
+        assert!(matches!(
+            client.fetch_input(2),
+            Err(ClientError::Status(404, _))
+        ));
+    }
+
+    #[test]
+    fn submit_answers() {
+        let server = server().with_cooldown(Duration::ZERO);
+        let client = Client::with_base_url(server.base_url(), SESSION);
+        let submit = |part, answer| client.submit_answer(1, part, answer).unwrap();
+
+        assert_eq!(submit(1, "12"), Verdict::Incorrect(Some(Hint::TooHigh)));
+        assert_eq!(submit(1, "10"), Verdict::Incorrect(Some(Hint::TooLow)));
+        assert_eq!(submit(1, "eleven"), Verdict::Incorrect(None));
+        assert_eq!(submit(1, "11"), Verdict::Correct);
+        assert_eq!(submit(1, "11"), Verdict::WrongLevel);
+        assert_eq!(submit(2, "31"), Verdict::Correct);
+    }
+
+    #[test]
+    fn submit_too_soon() {
+        let server = server().with_cooldown(Duration::from_secs(60));
+        let client = Client::with_base_url(server.base_url(), SESSION);
+
+        assert!(matches!(
+            client.submit_answer(1, 1, "1").unwrap(),
+            Verdict::Incorrect(_)
+        ));
+        match client.submit_answer(1, 1, "11").unwrap() {
+            Verdict::TooSoon { wait: Some(wait) } => assert!(wait.ends_with('s')),
+            verdict => panic!("unexpected verdict {:?}", verdict),
+        }
+    }
+
+    #[test]
+    fn unexpected_response() {
+        assert!(matches!(
+            Verdict::from_response("<article><p>Something else</p></article>"),
+            Err(ClientError::UnexpectedResponse(_))
+        ));
+    }
+}
@@ -1,69 +1,914 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+    iter,
+    ops::{Add, AddAssign, Mul, Sub, SubAssign},
+    str::FromStr,
+};
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
-type Input = (Vec<u32>, Vec<u32>);
+use crate::{cache::Codec, error::ParseError};
 
-#[aoc_generator(day1)]
-fn parse(input: &str) -> Input {
-    let (mut left_list, mut right_list) = input
-        .lines()
-        .map(|line| {
-            let (left, right) = line.split_once("   ").expect("missing delimiter");
-            let left = left.parse::<u32>().expect("not a number");
-            let right = right.parse::<u32>().expect("not a number");
-            (left, right)
+/// How lists of unequal length are dealt with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LengthPolicy {
+    /// Reject the input.
+    #[default]
+    Error,
+    /// Drop the last entries of the longer list.
+    Truncate,
+    /// Append zeros to the shorter list.
+    PadWithZero,
+}
+
+impl FromStr for LengthPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(LengthPolicy::Error),
+            "truncate" => Ok(LengthPolicy::Truncate),
+            "pad" => Ok(LengthPolicy::PadWithZero),
+            _ => Err(format!("unknown length policy '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for LengthPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthPolicy::Error => write!(f, "error"),
+            LengthPolicy::Truncate => write!(f, "truncate"),
+            LengthPolicy::PadWithZero => write!(f, "pad"),
+        }
+    }
+}
+
+/// Integer types usable as location ids.
+pub trait LocationId: Copy + Ord + Hash + Default + FromStr + fmt::Display {
+    /// Wider type in which differences and products of ids are summed up.
+    type Sum: Copy
+        + Ord
+        + Default
+        + fmt::Debug
+        + fmt::Display
+        + Add<Output = Self::Sum>
+        + Sub<Output = Self::Sum>
+        + Mul<Output = Self::Sum>
+        + AddAssign
+        + SubAssign
+        + iter::Sum;
+
+    fn widen(self) -> Self::Sum;
+    fn count(n: usize) -> Self::Sum;
+    /// `sum + d * d`, or `None` if that does not fit into [`Self::Sum`].
+    fn checked_add_square(sum: Self::Sum, d: Self::Sum) -> Option<Self::Sum>;
+
+    fn abs_diff(self, other: Self) -> Self::Sum {
+        let (a, b) = (self.widen(), other.widen());
+        if a > b {
+            a - b
+        } else {
+            b - a
+        }
+    }
+}
+
+macro_rules! impl_location_id {
+    ($t:ty, $sum:ty) => {
+        impl LocationId for $t {
+            type Sum = $sum;
+
+            fn widen(self) -> Self::Sum {
+                <$sum>::from(self)
+            }
+
+            fn count(n: usize) -> Self::Sum {
+                <$sum>::try_from(n).expect("count fits into the sum type")
+            }
+
+            fn checked_add_square(sum: Self::Sum, d: Self::Sum) -> Option<Self::Sum> {
+                d.checked_mul(d).and_then(|square| sum.checked_add(square))
+            }
+        }
+    };
+}
+
+impl_location_id!(u32, u128);
+impl_location_id!(u64, u128);
+impl_location_id!(i64, i128);
+
+/// Ways of comparing two lists of location ids. All metrics pair the entries
+/// of both lists by position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    /// Sum of absolute differences.
+    L1,
+    /// Sum of squared differences. This cannot overflow for `u32` ids, but a
+    /// single difference of `u64::MAX` already squares to nearly `u128::MAX`,
+    /// so it may overflow [`LocationId::Sum`] for 64-bit ids.
+    L2,
+    /// Largest absolute difference.
+    MaxDifference,
+    /// Number of discordant pairs, i.e. pairs of positions whose entries are
+    /// ordered differently in both lists. This is always 0 for sorted lists,
+    /// see [`parse_unsorted`].
+    KendallTau,
+    /// Sum of every left value times its number of occurrences in the right
+    /// list. Unlike the other metrics this does not depend on the order.
+    Similarity,
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "l1" => Ok(Metric::L1),
+            "l2" => Ok(Metric::L2),
+            "max" => Ok(Metric::MaxDifference),
+            "kendall-tau" => Ok(Metric::KendallTau),
+            "similarity" => Ok(Metric::Similarity),
+            _ => Err(format!("unknown metric '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::L1 => write!(f, "l1"),
+            Metric::L2 => write!(f, "l2"),
+            Metric::MaxDifference => write!(f, "max"),
+            Metric::KendallTau => write!(f, "kendall-tau"),
+            Metric::Similarity => write!(f, "similarity"),
+        }
+    }
+}
+
+impl Metric {
+    /// Compares `left` and `right`, extra entries of the longer list are
+    /// ignored. Returns `None` if the result does not fit into
+    /// [`LocationId::Sum`], which only happens for [`Metric::L2`].
+    pub fn compute<T: LocationId>(self, left: &[T], right: &[T]) -> Option<T::Sum> {
+        let mut differences = left
+            .iter()
+            .zip(right)
+            .map(|(left, right)| left.abs_diff(*right));
+        let result = match self {
+            Metric::L1 => differences.sum(),
+            Metric::L2 => {
+                return differences.try_fold(T::Sum::default(), T::checked_add_square);
+            }
+            Metric::MaxDifference => differences.max().unwrap_or_default(),
+            Metric::KendallTau => T::count(discordant_pairs(left, right)),
+            Metric::Similarity => {
+                let mut counts = HashMap::new();
+                right
+                    .iter()
+                    .for_each(|r| *counts.entry(r).or_insert(0) += 1);
+                left.iter()
+                    .map(|l| l.widen() * T::count(counts.get(l).copied().unwrap_or(0)))
+                    .sum()
+            }
+        };
+        Some(result)
+    }
+}
+
+/// Counts the pairs of positions `i`, `j` with `left[i] < left[j]` and
+/// `right[i] > right[j]` in O(n log n).
+fn discordant_pairs<T: Ord + Copy>(left: &[T], right: &[T]) -> usize {
+    let mut pairs = left.iter().zip(right).collect::<Vec<_>>();
+    // sorting ties by their right value means pairs with equal left values
+    // are never counted
+    pairs.sort();
+    let mut right = pairs.into_iter().map(|(_, r)| *r).collect::<Vec<_>>();
+    count_inversions(&mut right)
+}
+
+/// Sorts `values` with merge sort, counting the pairs which are out of order.
+fn count_inversions<T: Ord + Copy>(values: &mut [T]) -> usize {
+    if values.len() < 2 {
+        return 0;
+    }
+    let (left, right) = values.split_at_mut(values.len() / 2);
+    let mut inversions = count_inversions(left) + count_inversions(right);
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if right[j] < left[i] {
+            // right[j] is smaller than all remaining left values
+            inversions += left.len() - i;
+            merged.push(right[j]);
+            j += 1;
+        } else {
+            merged.push(left[i]);
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&left[i..]);
+    merged.extend_from_slice(&right[j..]);
+    values.copy_from_slice(&merged);
+    inversions
+}
+
+/// The location lists. The answers to part 1 and 2 are the [`Metric::L1`] and
+/// [`Metric::Similarity`] of the sorted lists.
+pub struct Input<T = u32> {
+    left_list: Vec<T>,
+    right_list: Vec<T>,
+    length_policy: LengthPolicy,
+    /// Lengths of the left and right list before applying the length policy.
+    original_lengths: (usize, usize),
+}
+
+/// A pair of the sorted lists and its share of the total distance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PairContribution<T: LocationId> {
+    /// Index of the pair in the sorted lists.
+    pub position: usize,
+    pub left: T,
+    pub right: T,
+    pub difference: T::Sum,
+}
+
+/// A location id and its share of the similarity score.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdContribution<T: LocationId> {
+    pub id: T,
+    pub left_count: usize,
+    pub right_count: usize,
+    /// `id` times `right_count`, once for every occurrence in the left list.
+    pub contribution: T::Sum,
+}
+
+impl<T: LocationId> Input<T> {
+    /// See [`Metric::compute`].
+    pub fn metric(&self, metric: Metric) -> Option<T::Sum> {
+        metric.compute(&self.left_list, &self.right_list)
+    }
+
+    /// Returns the `k` pairs with the largest differences, ties are ordered
+    /// by position.
+    pub fn top_differences(&self, k: usize) -> Vec<PairContribution<T>> {
+        let mut pairs = self
+            .left_list
+            .iter()
+            .zip(&self.right_list)
+            .enumerate()
+            .map(|(position, (left, right))| PairContribution {
+                position,
+                left: *left,
+                right: *right,
+                difference: left.abs_diff(*right),
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_by_key(|pair| Reverse(pair.difference));
+        pairs.truncate(k);
+        pairs
+    }
+
+    /// Returns the `k` ids occurring in both lists with the largest
+    /// contributions to the similarity score, ties are ordered by id.
+    pub fn top_similarity(&self, k: usize) -> Vec<IdContribution<T>> {
+        let mut counts = BTreeMap::new();
+        self.left_list
+            .iter()
+            .for_each(|id| counts.entry(*id).or_insert((0, 0)).0 += 1);
+        self.right_list
+            .iter()
+            .for_each(|id| counts.entry(*id).or_insert((0, 0)).1 += 1);
+
+        let mut ids = counts
+            .into_iter()
+            .filter(|(_, (left_count, right_count))| *left_count > 0 && *right_count > 0)
+            .map(|(id, (left_count, right_count))| IdContribution {
+                id,
+                left_count,
+                right_count,
+                contribution: id.widen() * T::count(left_count) * T::count(right_count),
+            })
+            .collect::<Vec<_>>();
+        ids.sort_by_key(|id| Reverse(id.contribution));
+        ids.truncate(k);
+        ids
+    }
+}
+
+impl<T: Codec> Codec for Input<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.left_list.encode(out);
+        self.right_list.encode(out);
+        (self.length_policy as u8).encode(out);
+        self.original_lengths.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let left_list: Vec<T> = Codec::decode(input)?;
+        let right_list: Vec<T> = Codec::decode(input)?;
+        let length_policy = match u8::decode(input)? {
+            0 => LengthPolicy::Error,
+            1 => LengthPolicy::Truncate,
+            2 => LengthPolicy::PadWithZero,
+            _ => return None,
+        };
+        let (left_len, right_len): (usize, usize) = Codec::decode(input)?;
+        // explain relies on the lists being adjusted as the policy demands
+        let len = match length_policy {
+            LengthPolicy::Error if left_len == right_len => left_len,
+            LengthPolicy::Error => return None,
+            LengthPolicy::Truncate => left_len.min(right_len),
+            LengthPolicy::PadWithZero => left_len.max(right_len),
+        };
+        (left_list.len() == len && right_list.len() == len).then_some(Input {
+            left_list,
+            right_list,
+            length_policy,
+            original_lengths: (left_len, right_len),
         })
-        .collect::<Input>();
+    }
+}
 
-    left_list.sort();
-    right_list.sort();
+#[aoc_generator(day1)]
+pub fn parse(input: &str) -> Result<Input, ParseError> {
+    parse_with_policy(input, LengthPolicy::Error)
+}
 
-    (left_list, right_list)
+/// Parses the location lists and sorts them. Unless `length_policy` is
+/// [`LengthPolicy::Error`], a line may lack the left or right location id,
+/// e.g. `"3   "` or `"   4"`, and the resulting difference in length of both
+/// lists is dealt with according to `length_policy`.
+pub fn parse_with_policy<T: LocationId>(
+    input: &str,
+    length_policy: LengthPolicy,
+) -> Result<Input<T>, ParseError> {
+    let mut input = parse_unsorted(input, length_policy)?;
+    input.left_list.sort();
+    input.right_list.sort();
+    Ok(input)
+}
+
+/// Like [`parse_with_policy`], but keeps both lists in input order. The
+/// puzzle answers assume sorted lists, this is meant for order-dependent
+/// metrics such as [`Metric::KendallTau`].
+pub fn parse_unsorted<T: LocationId>(
+    input: &str,
+    length_policy: LengthPolicy,
+) -> Result<Input<T>, ParseError> {
+    let mut left_list = Vec::new();
+    let mut right_list = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let not_a_number = |_| ParseError::new(i + 1, "not a number");
+        let (left, right) = line
+            .split_once("   ")
+            .ok_or_else(|| ParseError::new(i + 1, "missing delimiter"))?;
+        if left.is_empty() && right.is_empty() {
+            return Err(ParseError::new(i + 1, "missing location ids"));
+        }
+        if (left.is_empty() || right.is_empty()) && length_policy == LengthPolicy::Error {
+            return Err(ParseError::new(i + 1, "missing location id"));
+        }
+        if !left.is_empty() {
+            left_list.push(left.parse().map_err(not_a_number)?);
+        }
+        if !right.is_empty() {
+            right_list.push(right.parse().map_err(not_a_number)?);
+        }
+    }
+
+    let original_lengths = (left_list.len(), right_list.len());
+    if left_list.len() != right_list.len() {
+        match length_policy {
+            LengthPolicy::Error => {
+                return Err(ParseError::input(format!(
+                    "left list has {} entries but right list has {}",
+                    left_list.len(),
+                    right_list.len()
+                )))
+            }
+            LengthPolicy::Truncate => {
+                let len = left_list.len().min(right_list.len());
+                left_list.truncate(len);
+                right_list.truncate(len);
+            }
+            LengthPolicy::PadWithZero => {
+                let len = left_list.len().max(right_list.len());
+                left_list.resize(len, T::default());
+                right_list.resize(len, T::default());
+            }
+        }
+    }
+
+    Ok(Input {
+        left_list,
+        right_list,
+        length_policy,
+        original_lengths,
+    })
 }
 
 #[aoc(day1, part1)]
-fn part1(input: &Input) -> String {
-    let (left_list, right_list) = input;
-
-    left_list
-        .iter()
-        .zip(right_list.iter())
-        .map(|(left, right)| left.abs_diff(*right))
-        .sum::<u32>()
+pub fn part1(input: &Input) -> String {
+    input
+        .metric(Metric::L1)
+        .expect("L1 of u32 ids fits into u128")
         .to_string()
 }
 
 #[aoc(day1, part2)]
-fn part2(input: &Input) -> String {
-    let (left_list, right_list) = input;
-
-    let mut right_list = right_list.iter().peekable();
-    let mut last_similarity_score: Option<(u32, u32)> = None;
-
-    left_list
-        .iter()
-        .map(|left| {
-            // early return if similarity score has been calculated in the previous iteration
-            if let Some((last_left, last_score)) = last_similarity_score {
-                if *left == last_left {
-                    return last_score;
+pub fn part2(input: &Input) -> String {
+    input
+        .metric(Metric::Similarity)
+        .expect("similarity fits into the sum type")
+        .to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn index(self) -> usize {
+        match self {
+            Side::Left => 0,
+            Side::Right => 1,
+        }
+    }
+}
+
+/// Smallest number of entries per [`Block`] the ids are split into.
+const MIN_BLOCK_LEN: usize = 16;
+
+/// An id occurring in either list.
+#[derive(Clone, Copy)]
+struct Entry<T> {
+    id: T,
+    /// Occurrences in the left and right list.
+    counts: [usize; 2],
+}
+
+/// A run of consecutive entries of an [`IncrementalInput`].
+///
+/// Between two consecutive ids, the number of left ids seen so far minus the
+/// number of right ids seen so far is the `level` of the first one. The block
+/// keeps the levels of all but its last entry relative to the start of the
+/// block, sorted, each weighted by the gap to the next id. This is enough to
+/// add up `gap * |level|` for any level at the start of the block with a
+/// binary search.
+struct Block<T: LocationId> {
+    entries: Vec<Entry<T>>,
+    /// Number of ids in the left and right list.
+    counts: [usize; 2],
+    /// Sum of the ids in the left and right list.
+    sums: [T::Sum; 2],
+    levels: Vec<i64>,
+    /// `weights[i]` is the sum of the gaps of `levels[..i]`.
+    weights: Vec<T::Sum>,
+    /// `raised[i]` is the sum of the gaps of `levels[..i]`, each multiplied
+    /// by its level minus the lowest level.
+    raised: Vec<T::Sum>,
+}
+
+impl<T: LocationId> Block<T> {
+    fn new(entries: Vec<Entry<T>>) -> Self {
+        let mut counts = [0; 2];
+        let mut sums = [T::Sum::default(); 2];
+        let mut level = 0;
+        let mut levels = Vec::with_capacity(entries.len());
+        for (entry, next) in entries
+            .iter()
+            .zip(entries.iter().skip(1).map(Some).chain([None]))
+        {
+            for side in 0..2 {
+                counts[side] += entry.counts[side];
+                sums[side] += entry.id.widen() * T::count(entry.counts[side]);
+            }
+            level += entry.counts[0] as i64 - entry.counts[1] as i64;
+            if let Some(next) = next {
+                levels.push((level, entry.id.abs_diff(next.id)));
+            }
+        }
+
+        levels.sort_by_key(|(level, _)| *level);
+        let lowest = levels.first().map_or(0, |(level, _)| *level);
+        let mut weights = vec![T::Sum::default()];
+        let mut raised = vec![T::Sum::default()];
+        for (level, gap) in &levels {
+            weights.push(*weights.last().unwrap() + *gap);
+            raised.push(*raised.last().unwrap() + *gap * T::count((level - lowest) as usize));
+        }
+        Block {
+            entries,
+            counts,
+            sums,
+            levels: levels.into_iter().map(|(level, _)| level).collect(),
+            weights,
+            raised,
+        }
+    }
+
+    /// The change of the level from the start to the end of the block.
+    fn step(&self) -> i64 {
+        self.counts[0] as i64 - self.counts[1] as i64
+    }
+
+    /// Sum of `gap * |start + level|` over all but the last entry.
+    fn area(&self, start: i64) -> T::Sum {
+        let Some(&lowest) = self.levels.first() else {
+            return T::Sum::default();
+        };
+        let n = self.levels.len();
+        // levels[..split] lie below zero once `start` is added
+        let split = self.levels.partition_point(|level| start + level < 0);
+        let (weight_below, raised_below) = (self.weights[split], self.raised[split]);
+        let (weight_above, raised_above) = (
+            self.weights[n] - weight_below,
+            self.raised[n] - raised_below,
+        );
+
+        let offset = start + lowest;
+        let above = if offset >= 0 {
+            raised_above + weight_above * T::count(offset as usize)
+        } else {
+            raised_above - weight_above * T::count(offset.unsigned_abs() as usize)
+        };
+        let below = if split > 0 {
+            weight_below * T::count(offset.unsigned_abs() as usize) - raised_below
+        } else {
+            T::Sum::default()
+        };
+        above + below
+    }
+}
+
+/// Both location lists as sorted multisets, which keeps the answers up to date
+/// while location ids are inserted and removed.
+///
+/// A single insertion may change the partner of every entry in the sorted
+/// pairing, so the total distance is kept as the area between the cumulative
+/// counts of both lists instead. The distinct ids are split into blocks of
+/// about √n entries, each of which adds up its share of that area in
+/// O(log n), so both answers are updated in O(√n log n). If the lists differ
+/// in length, the largest entries of the longer list are left unpaired.
+pub struct IncrementalInput<T: LocationId = u32> {
+    blocks: Vec<Block<T>>,
+    /// Lengths of the left and right list.
+    lengths: [usize; 2],
+    /// Number of entries in all blocks.
+    distinct: usize,
+    similarity: T::Sum,
+    distance: T::Sum,
+}
+
+impl<T: LocationId> IncrementalInput<T> {
+    pub fn new(input: &Input<T>) -> Self {
+        let mut counts = BTreeMap::new();
+        for (side, list) in [&input.left_list, &input.right_list]
+            .into_iter()
+            .enumerate()
+        {
+            list.iter()
+                .for_each(|id| counts.entry(*id).or_insert([0; 2])[side] += 1);
+        }
+        let entries = counts
+            .into_iter()
+            .map(|(id, counts)| Entry { id, counts })
+            .collect::<Vec<_>>();
+
+        let mut incremental = IncrementalInput {
+            blocks: Vec::new(),
+            lengths: [input.left_list.len(), input.right_list.len()],
+            distinct: entries.len(),
+            similarity: entries
+                .iter()
+                .map(|entry| {
+                    entry.id.widen() * T::count(entry.counts[0]) * T::count(entry.counts[1])
+                })
+                .sum(),
+            distance: T::Sum::default(),
+        };
+        incremental.split_into_blocks(entries);
+        incremental.distance = incremental.compute_distance();
+        incremental
+    }
+
+    fn block_len(&self) -> usize {
+        self.distinct.isqrt().max(MIN_BLOCK_LEN)
+    }
+
+    fn split_into_blocks(&mut self, entries: Vec<Entry<T>>) {
+        self.blocks = entries
+            .chunks(self.block_len())
+            .map(|chunk| Block::new(chunk.to_vec()))
+            .collect();
+    }
+
+    /// Returns the index of the only block which may contain `id`.
+    fn block_of(&self, id: T) -> usize {
+        self.blocks
+            .partition_point(|block| block.entries.last().is_some_and(|entry| entry.id < id))
+            .min(self.blocks.len().saturating_sub(1))
+    }
+
+    /// Replaces the entries of `self.blocks[index]`, keeping the blocks close
+    /// to their intended length.
+    fn replace_block(&mut self, index: usize, entries: Vec<Entry<T>>) {
+        let block_len = self.block_len();
+        if entries.is_empty() {
+            self.blocks.remove(index);
+        } else if entries.len() > 2 * block_len {
+            let blocks = entries
+                .chunks(block_len)
+                .map(|chunk| Block::new(chunk.to_vec()))
+                .collect::<Vec<_>>();
+            self.blocks.splice(index..=index, blocks);
+        } else {
+            self.blocks[index] = Block::new(entries);
+        }
+
+        if self.blocks.len() > 2 * self.distinct.div_ceil(block_len) + 1 {
+            let entries = self
+                .blocks
+                .drain(..)
+                .flat_map(|block| block.entries)
+                .collect();
+            self.split_into_blocks(entries);
+        }
+    }
+
+    /// Sums up the differences of the sorted pairs as the area between the
+    /// cumulative counts of both lists, up to the largest paired id of the
+    /// longer list. Ids of the shorter list beyond it are paired with that id
+    /// or smaller ones.
+    fn compute_distance(&self) -> T::Sum {
+        let pairs = self.lengths[0].min(self.lengths[1]);
+        if pairs == 0 {
+            return T::Sum::default();
+        }
+        let longer = if self.lengths[0] >= self.lengths[1] {
+            0
+        } else {
+            1
+        };
+        let shorter = 1 - longer;
+
+        let mut remaining = pairs;
+        let mut level = 0;
+        let mut distance = T::Sum::default();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let next_block = self.blocks.get(index + 1);
+            if block.counts[longer] < remaining {
+                remaining -= block.counts[longer];
+                distance += block.area(level);
+                level += block.step();
+                let (last, next) = (
+                    block.entries.last().expect("blocks are not empty"),
+                    next_block.expect("the last paired id follows").entries[0],
+                );
+                distance += last.id.abs_diff(next.id) * T::count(level.unsigned_abs() as usize);
+                continue;
+            }
+
+            for (i, entry) in block.entries.iter().enumerate() {
+                if entry.counts[longer] >= remaining {
+                    let last_paired = entry.id;
+                    for later in &block.entries[i + 1..] {
+                        distance +=
+                            later.id.abs_diff(last_paired) * T::count(later.counts[shorter]);
+                    }
+                    for later in self.blocks[index + 1..].iter() {
+                        distance += later.sums[shorter]
+                            - last_paired.widen() * T::count(later.counts[shorter]);
+                    }
+                    return distance;
                 }
+                remaining -= entry.counts[longer];
+                level += entry.counts[0] as i64 - entry.counts[1] as i64;
+                let next = block.entries[i + 1];
+                distance += entry.id.abs_diff(next.id) * T::count(level.unsigned_abs() as usize);
             }
+        }
+        unreachable!("the longer list has more than {} ids", pairs)
+    }
+
+    pub fn insert(&mut self, side: Side, id: T) {
+        if self.blocks.is_empty() {
+            self.blocks.push(Block::new(Vec::new()));
+        }
+        let index = self.block_of(id);
+        let mut entries = std::mem::take(&mut self.blocks[index].entries);
+        let position = match entries.binary_search_by_key(&id, |entry| entry.id) {
+            Ok(position) => position,
+            Err(position) => {
+                entries.insert(position, Entry { id, counts: [0; 2] });
+                self.distinct += 1;
+                position
+            }
+        };
+        let entry = &mut entries[position];
+        entry.counts[side.index()] += 1;
+        self.similarity += id.widen() * T::count(entry.counts[1 - side.index()]);
+        self.lengths[side.index()] += 1;
+
+        self.replace_block(index, entries);
+        self.distance = self.compute_distance();
+    }
+
+    /// Removes one occurrence of `id`, returns whether `id` was present.
+    pub fn remove(&mut self, side: Side, id: T) -> bool {
+        if self.blocks.is_empty() {
+            return false;
+        }
+        let index = self.block_of(id);
+        let entries = &self.blocks[index].entries;
+        let Ok(position) = entries.binary_search_by_key(&id, |entry| entry.id) else {
+            return false;
+        };
+        if entries[position].counts[side.index()] == 0 {
+            return false;
+        }
+
+        let mut entries = std::mem::take(&mut self.blocks[index].entries);
+        let entry = &mut entries[position];
+        entry.counts[side.index()] -= 1;
+        self.similarity -= id.widen() * T::count(entry.counts[1 - side.index()]);
+        self.lengths[side.index()] -= 1;
+        if entry.counts == [0; 2] {
+            entries.remove(position);
+            self.distinct -= 1;
+        }
 
-            // calculate similarity score by counting all occurrences of left in right_list
-            let mut similarity_score = 0;
-            while let Some(right) = right_list.peek() {
-                match left.cmp(right) {
-                    std::cmp::Ordering::Less => break,
-                    std::cmp::Ordering::Equal => similarity_score += left,
-                    std::cmp::Ordering::Greater => (),
+        self.replace_block(index, entries);
+        self.distance = self.compute_distance();
+        true
+    }
+
+    /// The answer to part 1.
+    pub fn distance(&self) -> T::Sum {
+        self.distance
+    }
+
+    /// The answer to part 2.
+    pub fn similarity(&self) -> T::Sum {
+        self.similarity
+    }
+}
+
+/// Which columns [`Columns::matrix`] compares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// Every column against every column.
+    Pairwise,
+    /// Every column against the first one.
+    AgainstFirst,
+}
+
+/// Location lists with any number of columns, each sorted.
+pub struct Columns<T = u32> {
+    columns: Vec<Vec<T>>,
+}
+
+/// Parses lines of whitespace separated location ids, all lines must have the
+/// same number of columns.
+pub fn parse_columns<T: LocationId>(input: &str) -> Result<Columns<T>, ParseError> {
+    let mut columns: Vec<Vec<T>> = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let ids = line
+            .split_whitespace()
+            .map(|id| id.parse())
+            .collect::<Result<Vec<T>, _>>()
+            .map_err(|_| ParseError::new(i + 1, "not a number"))?;
+        if ids.is_empty() {
+            return Err(ParseError::new(i + 1, "missing location ids"));
+        }
+        if i == 0 {
+            columns = vec![Vec::new(); ids.len()];
+        }
+        if ids.len() != columns.len() {
+            return Err(ParseError::new(
+                i + 1,
+                format!("expected {} columns but found {}", columns.len(), ids.len()),
+            ));
+        }
+        columns
+            .iter_mut()
+            .zip(ids)
+            .for_each(|(column, id)| column.push(id));
+    }
+
+    columns.iter_mut().for_each(|column| column.sort());
+    Ok(Columns { columns })
+}
+
+impl<T: LocationId> Columns<T> {
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Computes `metric` for the columns selected by `comparison`. Row `i`
+    /// of the result holds the comparisons of column `i`, or of the first
+    /// column for [`Comparison::AgainstFirst`], with every column.
+    pub fn matrix(&self, metric: Metric, comparison: Comparison) -> Vec<Vec<Option<T::Sum>>> {
+        let rows = match comparison {
+            Comparison::Pairwise => &self.columns[..],
+            Comparison::AgainstFirst => &self.columns[..self.len().min(1)],
+        };
+        rows.iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .map(|column| metric.compute(row, column))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Summarizes the value range and the number of duplicates of both lists.
+pub fn describe<T: LocationId>(input: &Input<T>) -> String {
+    let describe_list = |list: &[T]| match (list.first(), list.last()) {
+        (Some(min), Some(max)) => {
+            let duplicates = list.windows(2).filter(|w| w[0] == w[1]).count();
+            format!(
+                "{} values in {}..={}, {} duplicates",
+                list.len(),
+                min,
+                max,
+                duplicates
+            )
+        }
+        _ => "empty".to_string(),
+    };
+
+    format!(
+        "left list: {}\nright list: {}",
+        describe_list(&input.left_list),
+        describe_list(&input.right_list)
+    )
+}
+
+/// Number of pairs and ids listed by [`explain`].
+const EXPLAIN_TOP: usize = 3;
+
+/// Explains how the lists were made equally long and which pairs and ids
+/// contribute most to the answers.
+pub fn explain<T: LocationId>(input: &Input<T>) -> String {
+    let (left_len, right_len) = input.original_lengths;
+    let adjustment = match left_len.cmp(&right_len) {
+        Ordering::Equal => "lists are equally long".to_string(),
+        ordering => {
+            let (longer, shorter) = match ordering {
+                Ordering::Greater => ("left", "right"),
+                _ => ("right", "left"),
+            };
+            let n = left_len.abs_diff(right_len);
+            match input.length_policy {
+                LengthPolicy::Truncate => format!("dropped {} entries of the {} list", n, longer),
+                LengthPolicy::PadWithZero => {
+                    format!("appended {} zeros to the {} list", n, shorter)
                 }
-                right_list.next();
+                LengthPolicy::Error => unreachable!("rejected by parse_with_policy"),
             }
-            last_similarity_score = Some((*left, similarity_score));
-            similarity_score
-        })
-        .sum::<u32>()
-        .to_string()
+        }
+    };
+
+    let mut explanation = format!(
+        "length policy: {} ({})\nleft list: {} entries\nright list: {} entries\npairs: {}",
+        input.length_policy,
+        adjustment,
+        left_len,
+        right_len,
+        input.left_list.len()
+    );
+    explanation.push_str("\nlargest differences:");
+    for pair in input.top_differences(EXPLAIN_TOP) {
+        explanation.push_str(&format!(
+            "\n  pair {}: {} and {} differ by {}",
+            pair.position + 1,
+            pair.left,
+            pair.right,
+            pair.difference
+        ));
+    }
+    explanation.push_str("\nlargest similarity contributions:");
+    for id in input.top_similarity(EXPLAIN_TOP) {
+        explanation.push_str(&format!(
+            "\n  {}: {} times left, {} times right, contributes {}",
+            id.id, id.left_count, id.right_count, id.contribution
+        ));
+    }
+    explanation
 }
 
 #[cfg(test)]
@@ -74,11 +919,315 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), "11");
+        assert_eq!(part1(&parse(EXAMPLE).unwrap()), "11");
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), "31");
+        assert_eq!(part2(&parse(EXAMPLE).unwrap()), "31");
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE).unwrap()),
+            "left list: 6 values in 1..=4, 2 duplicates\n\
+             right list: 6 values in 3..=9, 2 duplicates"
+        );
+    }
+
+    #[test]
+    fn unequal_lengths() {
+        let input = "3   4\n4   3\n2   ";
+        assert!(parse(input).is_err());
+        assert_eq!(
+            parse("3   4\n4").err(),
+            Some(ParseError::new(2, "missing delimiter"))
+        );
+        assert!(parse_with_policy::<u32>("3   4\n4", LengthPolicy::PadWithZero).is_err());
+        assert!(parse("3   4\n   ").is_err());
+        assert_eq!(
+            parse("3   4\n2   \n   5").err(),
+            Some(ParseError::new(2, "missing location id"))
+        );
+        assert_eq!(
+            parse("3   4\n   5").err(),
+            Some(ParseError::new(2, "missing location id"))
+        );
+        let balanced = parse_with_policy::<u32>("3   4\n2   \n   5", LengthPolicy::Truncate);
+        assert_eq!(part1(&balanced.unwrap()), "4");
+
+        let truncated = parse_with_policy::<u32>(input, LengthPolicy::Truncate).unwrap();
+        assert_eq!(truncated.left_list, [3, 4]);
+        assert_eq!(truncated.right_list, [3, 4]);
+
+        let padded = parse_with_policy(input, LengthPolicy::PadWithZero).unwrap();
+        assert_eq!(padded.left_list, [2, 3, 4]);
+        assert_eq!(padded.right_list, [0, 3, 4]);
+        assert_eq!(part1(&padded), "2");
+    }
+
+    #[test]
+    fn explain_length_policy() {
+        let input = parse_with_policy::<u32>("3   4\n4   3\n2   ", LengthPolicy::Truncate).unwrap();
+        assert_eq!(
+            explain(&input),
+            "length policy: truncate (dropped 1 entries of the left list)\n\
+             left list: 3 entries\n\
+             right list: 2 entries\n\
+             pairs: 2\n\
+             largest differences:\n  \
+               pair 1: 3 and 3 differ by 0\n  \
+               pair 2: 4 and 4 differ by 0\n\
+             largest similarity contributions:\n  \
+               4: 1 times left, 1 times right, contributes 4\n  \
+               3: 1 times left, 1 times right, contributes 3"
+        );
+        assert!(explain(&parse(EXAMPLE).unwrap())
+            .starts_with("length policy: error (lists are equally long)"));
+    }
+
+    #[test]
+    fn decode_checks_lengths() {
+        use crate::cache::{from_bytes, to_bytes};
+
+        let input = parse_with_policy::<u32>("3   4\n4   3\n2   ", LengthPolicy::Truncate).unwrap();
+        let decoded = from_bytes::<Input>(&to_bytes(&input)).unwrap();
+        assert_eq!(decoded.left_list, input.left_list);
+        assert_eq!(decoded.original_lengths, (3, 2));
+
+        let mut bytes = to_bytes(&vec![1u32, 2]);
+        bytes.extend(to_bytes(&vec![3u32]));
+        bytes.extend(to_bytes(&1u8));
+        bytes.extend(to_bytes(&(2usize, 1usize)));
+        assert!(from_bytes::<Input>(&bytes).is_none());
+
+        let mut bytes = to_bytes(&vec![1u32]);
+        bytes.extend(to_bytes(&vec![3u32]));
+        bytes.extend(to_bytes(&0u8));
+        bytes.extend(to_bytes(&(2usize, 1usize)));
+        assert!(from_bytes::<Input>(&bytes).is_none());
+    }
+
+    #[test]
+    fn top_contributors_example() {
+        let input = parse(EXAMPLE).unwrap();
+        let differences = input.top_differences(3);
+        assert_eq!(
+            differences
+                .iter()
+                .map(|pair| (pair.position, pair.difference))
+                .collect::<Vec<_>>(),
+            [(5, 5), (0, 2), (4, 2)]
+        );
+        assert_eq!((differences[0].left, differences[0].right), (4, 9));
+
+        let ids = input.top_similarity(3);
+        assert_eq!(
+            ids.iter()
+                .map(|id| (id.id, id.contribution))
+                .collect::<Vec<_>>(),
+            [(3, 27), (4, 4)]
+        );
+        assert_eq!(
+            Some(ids.iter().map(|id| id.contribution).sum::<u128>()),
+            input.metric(Metric::Similarity)
+        );
+        assert!(input.top_differences(0).is_empty());
+    }
+
+    #[test]
+    fn metrics_example() {
+        let input = parse(EXAMPLE).unwrap();
+        let metric = |name: &str| input.metric(name.parse().unwrap()).unwrap();
+        assert_eq!(metric("l1"), 11);
+        assert_eq!(metric("l2"), 35);
+        assert_eq!(metric("max"), 5);
+        assert_eq!(metric("kendall-tau"), 0);
+        assert_eq!(metric("similarity"), 31);
+        assert!("l3".parse::<Metric>().is_err());
+    }
+
+    #[test]
+    fn kendall_tau_in_input_order() {
+        let input = parse_unsorted(EXAMPLE, LengthPolicy::Error).unwrap();
+        assert_eq!(
+            input.metric(Metric::KendallTau),
+            Some(brute_force_discordant_pairs(
+                &input.left_list,
+                &input.right_list
+            ))
+        );
+        assert_eq!(
+            Metric::KendallTau.compute::<u32>(&[1, 2, 3], &[3, 2, 1]),
+            Some(3)
+        );
+        assert_eq!(
+            Metric::KendallTau.compute::<u32>(&[1, 1, 2], &[2, 1, 1]),
+            Some(1)
+        );
+        assert_eq!(input.metric(Metric::Similarity), Some(31));
+    }
+
+    fn brute_force_discordant_pairs(left: &[u32], right: &[u32]) -> u128 {
+        let n = left.len();
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .filter(|&(i, j)| left[i] < left[j] && right[i] > right[j])
+            .count() as u128
+    }
+
+    #[test]
+    fn negative_ids() {
+        let input = parse_with_policy::<i64>("-3   4\n4   -3", LengthPolicy::Error).unwrap();
+        assert_eq!(input.metric(Metric::L1), Some(0));
+        assert_eq!(input.metric(Metric::Similarity), Some(1));
+        assert!(parse("-3   4\n4   -3").is_err());
+
+        let input = parse_with_policy::<i64>("-5   7\n-5   -5", LengthPolicy::Error).unwrap();
+        assert_eq!(input.metric(Metric::L1), Some(12));
+        assert_eq!(input.metric(Metric::Similarity), Some(-10));
+    }
+
+    #[test]
+    fn large_differences() {
+        let max = u64::from(u32::MAX);
+        let input = format!("0   {}\n0   {}", u32::MAX, u32::MAX);
+        let input = parse(&input).unwrap();
+        assert_eq!(input.metric(Metric::L1), Some(2 * u128::from(max)));
+        assert_eq!(input.metric(Metric::L2), Some(2 * u128::from(max * max)));
+
+        let input = parse("0   4000000000\n0   4000000000").unwrap();
+        assert_eq!(input.metric(Metric::L2), Some(32_000_000_000_000_000_000));
+
+        let input = format!("{}   {}", i64::MIN, i64::MAX);
+        let input = parse_with_policy::<i64>(&input, LengthPolicy::Error).unwrap();
+        assert_eq!(input.metric(Metric::L1), Some(i128::from(u64::MAX)));
+        assert_eq!(input.metric(Metric::L2), None);
+
+        let max = u64::MAX;
+        let input = format!("0   {}", max);
+        let input = parse_with_policy::<u64>(&input, LengthPolicy::Error).unwrap();
+        assert_eq!(
+            input.metric(Metric::L2),
+            Some(u128::from(max) * u128::from(max))
+        );
+        let input = format!("0   {}\n0   {}", max, max);
+        let input = parse_with_policy::<u64>(&input, LengthPolicy::Error).unwrap();
+        assert_eq!(input.metric(Metric::L1), Some(2 * u128::from(max)));
+        assert_eq!(input.metric(Metric::L2), None);
+    }
+
+    #[test]
+    fn wide_ids() {
+        let max = u64::MAX;
+        let input = format!("{}   0\n{}   {}", max, max, max);
+        let input = parse_with_policy::<u64>(&input, LengthPolicy::Error).unwrap();
+        assert_eq!(input.metric(Metric::L1), Some(u128::from(max)));
+        assert_eq!(input.metric(Metric::Similarity), Some(2 * u128::from(max)));
+        assert!(parse(&format!("{}   0", max)).is_err());
+    }
+
+    #[test]
+    fn multiple_columns() {
+        let columns = parse_columns::<u32>("3 4 1\n4 3 3\n2 5 3").unwrap();
+        assert_eq!(columns.len(), 3);
+        assert_eq!(
+            columns.matrix(Metric::L1, Comparison::Pairwise),
+            [[0, 3, 2], [3, 0, 5], [2, 5, 0]].map(|row| row.map(Some))
+        );
+        assert_eq!(
+            columns.matrix(Metric::Similarity, Comparison::AgainstFirst),
+            [[Some(9), Some(7), Some(6)]]
+        );
+
+        let two_columns = parse_columns::<u32>(EXAMPLE).unwrap();
+        let input = parse(EXAMPLE).unwrap();
+        assert_eq!(
+            two_columns.matrix(Metric::L1, Comparison::AgainstFirst)[0][1],
+            input.metric(Metric::L1)
+        );
+
+        assert!(parse_columns::<u32>("1 2 3\n4 5").is_err());
+        assert_eq!(
+            parse_columns::<u32>("1 2\n\n3 4").err(),
+            Some(ParseError::new(2, "missing location ids"))
+        );
+        assert_eq!(
+            parse_columns::<u32>(" \n1 2").err(),
+            Some(ParseError::new(1, "missing location ids"))
+        );
+        assert!(parse_columns::<u32>("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn incremental_example() {
+        let mut incremental = IncrementalInput::new(&parse(EXAMPLE).unwrap());
+        assert_eq!(incremental.distance(), 11);
+        assert_eq!(incremental.similarity(), 31);
+
+        assert!(incremental.remove(Side::Right, 9));
+        assert!(!incremental.remove(Side::Right, 9));
+        incremental.insert(Side::Right, 3);
+        assert_eq!(incremental.distance(), 5);
+        assert_eq!(incremental.similarity(), 40);
+    }
+
+    #[test]
+    fn incremental_matches_recomputation() {
+        // few distinct ids fit into a single block, many are split into several
+        for (seed, ids) in [(1, 20), (2, 2000)] {
+            check_incremental(seed, ids);
+        }
+    }
+
+    fn check_incremental(seed: u64, ids: usize) {
+        let mut rng = crate::fuzz::Rng::new(seed);
+        let input = parse(EXAMPLE).unwrap();
+        let mut incremental = IncrementalInput::new(&input);
+        let (mut left_list, mut right_list) = (input.left_list, input.right_list);
+
+        for _ in 0..2000 {
+            let (side, list) = if rng.below(2) == 0 {
+                (Side::Left, &mut left_list)
+            } else {
+                (Side::Right, &mut right_list)
+            };
+            let id = rng.below(ids) as u32;
+            // inserting slightly more often lets the lists grow
+            if rng.below(5) < 3 {
+                incremental.insert(side, id);
+                list.push(id);
+            } else {
+                let position = list.iter().position(|v| *v == id);
+                assert_eq!(incremental.remove(side, id), position.is_some());
+                if let Some(position) = position {
+                    list.remove(position);
+                }
+            }
+
+            let mut sorted_left = left_list.clone();
+            let mut sorted_right = right_list.clone();
+            sorted_left.sort();
+            sorted_right.sort();
+            assert_eq!(
+                incremental.distance(),
+                Metric::L1.compute(&sorted_left, &sorted_right).unwrap()
+            );
+            assert_eq!(
+                incremental.similarity(),
+                Metric::Similarity.compute(&left_list, &right_list).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day1", EXAMPLE, |input| {
+            if let Ok(input) = parse(input) {
+                part1(&input);
+                part2(&input);
+            }
+        });
     }
 }
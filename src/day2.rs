@@ -1,82 +1,474 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
+    io::{self, BufRead},
+};
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
-type Input = Vec<Report>;
+use crate::error::ParseError;
+
+pub type Input = Vec<Report>;
 type Report = Vec<i64>;
 enum Levels {
     Increasing,
     Decreasing,
+    /// Steps may go in either direction.
+    Unordered,
+}
+
+/// Which directions the levels of a monotonic report may go in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Increasing,
+    Decreasing,
+    Either,
+}
+
+/// Rules deciding whether a report is safe. The default are the rules of the
+/// puzzle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SafetyPolicy {
+    /// Smallest allowed absolute difference of adjacent levels. Adjacent
+    /// levels may be equal if this is 0.
+    pub min_step: u64,
+    /// Largest allowed absolute difference of adjacent levels, must not be
+    /// smaller than `min_step`.
+    pub max_step: u64,
+    /// Whether all levels must go in the same direction.
+    pub monotonic: bool,
+    /// Whether adjacent levels may be equal even if `min_step` is greater
+    /// than 0.
+    pub allow_plateaus: bool,
+    /// Which directions a monotonic report may go in.
+    pub direction: Direction,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        SafetyPolicy {
+            min_step: 1,
+            max_step: 3,
+            monotonic: true,
+            allow_plateaus: false,
+            direction: Direction::Either,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    /// The orders a report is checked against, it is safe if it is safe in
+    /// any of them.
+    fn levels(&self) -> &'static [Levels] {
+        debug_assert!(
+            self.min_step <= self.max_step,
+            "min_step {} exceeds max_step {}",
+            self.min_step,
+            self.max_step
+        );
+        match (self.monotonic, self.direction) {
+            (false, _) => &[Levels::Unordered],
+            (true, Direction::Increasing) => &[Levels::Increasing],
+            (true, Direction::Decreasing) => &[Levels::Decreasing],
+            (true, Direction::Either) => &[Levels::Increasing, Levels::Decreasing],
+        }
+    }
+
+    fn is_safe_step(&self, levels: &Levels, from: i64, to: i64) -> bool {
+        let Some(step) = to.checked_sub(from) else {
+            return false;
+        };
+        if step == 0 {
+            return self.allow_plateaus || self.min_step == 0;
+        }
+        let direction_ok = match levels {
+            Levels::Increasing => step > 0,
+            Levels::Decreasing => step < 0,
+            Levels::Unordered => true,
+        };
+        direction_ok && (self.min_step..=self.max_step).contains(&step.unsigned_abs())
+    }
 }
 
 #[aoc_generator(day2)]
-fn parse(input: &str) -> Input {
+pub fn parse(input: &str) -> Result<Input, ParseError> {
     input
         .lines()
-        .map(|line| line.split(' ').map(|v| v.parse().unwrap()).collect())
+        .enumerate()
+        .map(|(i, line)| parse_report(line, i + 1))
         .collect()
 }
 
-#[aoc(day2, part1)]
-fn part1(input: &Input) -> String {
-    fn is_safe(report: &Report, levels: Levels) -> bool {
-        let range = match levels {
-            Levels::Increasing => 1..=3,
-            Levels::Decreasing => -3..=-1,
-        };
+fn parse_report(line: &str, line_number: usize) -> Result<Report, ParseError> {
+    line.split(' ')
+        .map(|v| {
+            v.parse()
+                .map_err(|_| ParseError::new(line_number, "not a number"))
+        })
+        .collect()
+}
 
-        report
-            .iter()
-            .skip(1)
-            .zip(report.iter())
-            .all(|(k, j)| range.contains(&(k - j)))
+/// Returns the minimum number of levels which have to be removed for the
+/// report to be safe in the order given by `levels`, or `None` if more than
+/// `max_removals` levels would have to be removed.
+fn min_removals(
+    report: &[i64],
+    policy: &SafetyPolicy,
+    levels: &Levels,
+    max_removals: usize,
+) -> Option<usize> {
+    // removals[i] is the minimum number of levels to remove from report[..=i]
+    // such that the remaining levels are safe and end with report[i]. Keeping
+    // report[j] directly before report[i] removes the i - j - 1 levels in
+    // between, so only the last max_removals + 1 levels are candidates for j.
+    let mut removals = Vec::with_capacity(report.len());
+    for i in 0..report.len() {
+        let kept_predecessor = (i.saturating_sub(max_removals + 1)..i)
+            .filter(|&j| policy.is_safe_step(levels, report[j], report[i]))
+            .map(|j| removals[j] + (i - j - 1))
+            .min();
+        removals.push(kept_predecessor.map_or(i, |r: usize| r.min(i)));
+    }
+
+    let min_removals = (0..report.len())
+        .map(|i| removals[i] + (report.len() - 1 - i))
+        .min()
+        .unwrap_or(0);
+    (min_removals <= max_removals).then_some(min_removals)
+}
+
+/// Returns the index of the first level of the first unsafe step in the order
+/// given by `levels`, ignoring the level at index `skip`.
+fn first_unsafe_step(
+    report: &[i64],
+    policy: &SafetyPolicy,
+    levels: &Levels,
+    skip: Option<usize>,
+) -> Option<usize> {
+    let mut kept = (0..report.len()).filter(|&i| Some(i) != skip);
+    let mut previous = kept.next()?;
+    for i in kept {
+        if !policy.is_safe_step(levels, report[previous], report[i]) {
+            return Some(previous);
+        }
+        previous = i;
     }
+    None
+}
 
+/// Returns the index of a level whose removal makes the report safe, given
+/// the index of its first unsafe step. One of the levels of the first unsafe
+/// step has to be removed, so only those two are tried.
+fn removable_level(
+    report: &[i64],
+    policy: &SafetyPolicy,
+    levels: &Levels,
+    unsafe_step: usize,
+) -> Option<usize> {
+    [unsafe_step, unsafe_step + 1]
+        .into_iter()
+        .find(|&skip| first_unsafe_step(report, policy, levels, Some(skip)).is_none())
+}
+
+/// Same as [`min_removals`] with `max_removals` of 1, but in O(n) and without
+/// allocating.
+fn is_safe_with_dampener(report: &[i64], policy: &SafetyPolicy, levels: &Levels) -> bool {
+    match first_unsafe_step(report, policy, levels, None) {
+        Some(i) => removable_level(report, policy, levels, i).is_some(),
+        None => true,
+    }
+}
+
+/// Whether the report can be made safe by removing up to `max_removals`
+/// levels.
+pub fn is_safe(report: &[i64], policy: &SafetyPolicy, max_removals: usize) -> bool {
+    policy.levels().iter().any(|levels| match max_removals {
+        0 => first_unsafe_step(report, policy, levels, None).is_none(),
+        1 => is_safe_with_dampener(report, policy, levels),
+        _ => min_removals(report, policy, levels, max_removals).is_some(),
+    })
+}
+
+/// Counts the reports which can be made safe by removing up to
+/// `max_removals` levels.
+pub fn count_safe(input: &Input, policy: &SafetyPolicy, max_removals: usize) -> usize {
     input
         .iter()
-        .filter(|report| is_safe(report, Levels::Increasing) || is_safe(report, Levels::Decreasing))
+        .filter(|report| is_safe(report, policy, max_removals))
         .count()
-        .to_string()
 }
 
-#[aoc(day2, part2)]
-fn part2(input: &Input) -> String {
-    fn is_safe(report: &Report, levels: &Levels, detected_bad_level: bool) -> bool {
-        let range = match levels {
-            Levels::Increasing => 1..=3,
-            Levels::Decreasing => -3..=-1,
-        };
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Classification {
+    SafeIncreasing,
+    SafeDecreasing,
+    /// Safe under a policy which does not require monotonic reports.
+    Safe,
+    /// Safe after removing the level at the given index.
+    SafeWithoutLevel(usize),
+    Unsafe,
+}
 
-        let bad_level = report
-            .iter()
-            .skip(1)
-            .zip(report.iter())
-            .position(|(k, j)| !range.contains(&(k - j)));
-
-        if bad_level.is_some() && detected_bad_level {
-            false
-        } else if let Some(i) = bad_level {
-            let report_without_level_at = |i: usize| {
-                let mut report = report.clone();
-                report.remove(i);
-                report
-            };
+/// Two adjacent levels violating the safety policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsafeStep {
+    /// Index of the first of both levels.
+    pub index: usize,
+    pub from: i64,
+    pub to: i64,
+}
 
-            let report_a = report_without_level_at(i + 1);
-            let report_b = report_without_level_at(i);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub classification: Classification,
+    /// The first unsafe step in the direction the report was checked in. For
+    /// unsafe reports this is the direction in which the report stays safe
+    /// for longer.
+    pub first_unsafe_step: Option<UnsafeStep>,
+}
 
-            is_safe(&report_a, levels, true) || is_safe(&report_b, levels, true)
-        } else {
-            true
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.classification {
+            Classification::SafeIncreasing => write!(f, "safe, increasing")?,
+            Classification::SafeDecreasing => write!(f, "safe, decreasing")?,
+            Classification::Safe => write!(f, "safe")?,
+            Classification::SafeWithoutLevel(i) => write!(f, "safe without level {}", i)?,
+            Classification::Unsafe => write!(f, "unsafe")?,
         }
+        if let Some(step) = self.first_unsafe_step {
+            write!(
+                f,
+                ", first unsafe step at level {}: {} -> {}",
+                step.index, step.from, step.to
+            )?;
+        }
+        Ok(())
     }
+}
+
+/// Classifies a report, allowing the removal of one level as in part 2.
+pub fn diagnose(report: &[i64], policy: &SafetyPolicy) -> Diagnosis {
+    let unsafe_steps = policy
+        .levels()
+        .iter()
+        .map(|levels| (levels, first_unsafe_step(report, policy, levels, None)));
+
+    let mut furthest_unsafe_step: Option<UnsafeStep> = None;
+    let mut removable = None;
+    for (levels, step) in unsafe_steps {
+        let Some(i) = step else {
+            let classification = match levels {
+                Levels::Increasing => Classification::SafeIncreasing,
+                Levels::Decreasing => Classification::SafeDecreasing,
+                Levels::Unordered => Classification::Safe,
+            };
+            return Diagnosis {
+                classification,
+                first_unsafe_step: None,
+            };
+        };
+        let step = UnsafeStep {
+            index: i,
+            from: report[i],
+            to: report[i + 1],
+        };
+        if removable.is_none() {
+            removable = removable_level(report, policy, levels, i).map(|skip| (skip, step));
+        }
+        if furthest_unsafe_step.is_none_or(|furthest| furthest.index < i) {
+            furthest_unsafe_step = Some(step);
+        }
+    }
+
+    match removable {
+        Some((skip, step)) => Diagnosis {
+            classification: Classification::SafeWithoutLevel(skip),
+            first_unsafe_step: Some(step),
+        },
+        None => Diagnosis {
+            classification: Classification::Unsafe,
+            first_unsafe_step: furthest_unsafe_step,
+        },
+    }
+}
+
+#[aoc(day2, part1)]
+pub fn part1(input: &Input) -> String {
+    part1_with_policy(input, &SafetyPolicy::default())
+}
+
+pub fn part1_with_policy(input: &Input, policy: &SafetyPolicy) -> String {
+    count_safe(input, policy, 0).to_string()
+}
 
+#[aoc(day2, part2)]
+pub fn part2(input: &Input) -> String {
+    part2_with_policy(input, &SafetyPolicy::default())
+}
+
+pub fn part2_with_policy(input: &Input, policy: &SafetyPolicy) -> String {
+    count_safe(input, policy, 1).to_string()
+}
+
+/// Summarizes the number of reports and their lengths.
+pub fn describe(input: &Input) -> String {
+    let mut reports_by_length = BTreeMap::<usize, usize>::new();
+    for report in input {
+        *reports_by_length.entry(report.len()).or_default() += 1;
+    }
+
+    format!(
+        "reports: {}\nreport lengths: {}",
+        input.len(),
+        format_histogram(&reports_by_length)
+    )
+}
+
+fn format_histogram<K: fmt::Display>(histogram: &BTreeMap<K, usize>) -> String {
+    histogram
+        .iter()
+        .map(|(value, count)| format!("{} ({}x)", value, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists the diagnosis of every report.
+pub fn explain(input: &Input) -> String {
     input
         .iter()
-        .filter(|report| {
-            is_safe(report, &Levels::Increasing, false)
-                || is_safe(report, &Levels::Decreasing, false)
+        .enumerate()
+        .map(|(i, report)| {
+            format!(
+                "report {}: {}",
+                i + 1,
+                diagnose(report, &SafetyPolicy::default())
+            )
         })
-        .count()
-        .to_string()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Running statistics over the reports classified by a [`Classifier`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub reports: usize,
+    /// Reports which are safe, possibly after removing a level.
+    pub safe: usize,
+    /// Reports which are only safe after removing a level.
+    pub dampened: usize,
+    pub report_lengths: BTreeMap<usize, usize>,
+    /// Differences of the first unsafe steps of all reports which are not
+    /// safe without removing a level.
+    pub failing_steps: BTreeMap<i128, usize>,
+}
+
+impl Statistics {
+    pub fn safe_ratio(&self) -> f64 {
+        if self.reports == 0 {
+            return 0.0;
+        }
+        self.safe as f64 / self.reports as f64
+    }
+
+    fn record(&mut self, report: &[i64], diagnosis: &Diagnosis) {
+        self.reports += 1;
+        match diagnosis.classification {
+            Classification::Unsafe => (),
+            Classification::SafeWithoutLevel(_) => {
+                self.safe += 1;
+                self.dampened += 1;
+            }
+            _ => self.safe += 1,
+        }
+        *self.report_lengths.entry(report.len()).or_default() += 1;
+        if let Some(step) = diagnosis.first_unsafe_step {
+            let difference = i128::from(step.to) - i128::from(step.from);
+            *self.failing_steps.entry(difference).or_default() += 1;
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "reports: {}\nsafe: {} ({:.1}%, {} after removing a level)\n\
+             report lengths: {}\nfailing steps: {}",
+            self.reports,
+            self.safe,
+            100.0 * self.safe_ratio(),
+            self.dampened,
+            format_histogram(&self.report_lengths),
+            format_histogram(&self.failing_steps)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "failed to read report: {}", e),
+            StreamError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for StreamError {}
+
+/// Classifies reports as their lines arrive, see [`classify`].
+pub struct Classifier<L> {
+    lines: L,
+    policy: SafetyPolicy,
+    line_number: usize,
+    statistics: Statistics,
+}
+
+impl<L: Iterator<Item = io::Result<String>>> Classifier<L> {
+    pub fn new(lines: L, policy: SafetyPolicy) -> Self {
+        Classifier {
+            lines,
+            policy,
+            line_number: 0,
+            statistics: Statistics::default(),
+        }
+    }
+
+    /// Statistics over the reports classified so far.
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+impl<L: Iterator<Item = io::Result<String>>> Iterator for Classifier<L> {
+    type Item = Result<Diagnosis, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.lines.next()? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(StreamError::Io(e))),
+        };
+        self.line_number += 1;
+        let report = match parse_report(&line, self.line_number) {
+            Ok(report) => report,
+            Err(e) => return Some(Err(StreamError::Parse(e))),
+        };
+        let diagnosis = diagnose(&report, &self.policy);
+        self.statistics.record(&report, &diagnosis);
+        Some(Ok(diagnosis))
+    }
+}
+
+/// Classifies the reports read line by line from `reader`.
+pub fn classify<R: BufRead>(reader: R, policy: SafetyPolicy) -> Classifier<io::Lines<R>> {
+    Classifier::new(reader.lines(), policy)
 }
 
 #[cfg(test)]
@@ -87,11 +479,270 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), "2");
+        assert_eq!(part1(&parse(EXAMPLE).unwrap()), "2");
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), "4");
+        assert_eq!(part2(&parse(EXAMPLE).unwrap()), "4");
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE).unwrap()),
+            "reports: 6\nreport lengths: 5 (6x)"
+        );
+    }
+
+    #[test]
+    fn remove_multiple_levels() {
+        let input = parse(EXAMPLE).unwrap();
+        let policy = SafetyPolicy::default();
+        assert_eq!(count_safe(&input, &policy, 2), 6);
+
+        assert!(!is_safe(&[1, 9, 2, 8, 3], &policy, 1));
+        assert!(is_safe(&[1, 9, 2, 8, 3], &policy, 2));
+        assert!(is_safe(&[], &policy, 0));
+        assert!(is_safe(&[7], &policy, 0));
+    }
+
+    /// Tries every way of removing up to `max_removals` levels.
+    fn is_safe_brute_force(report: &[i64], max_removals: usize) -> bool {
+        let n = report.len();
+        (0u32..1 << n)
+            .filter(|removed| removed.count_ones() as usize <= max_removals)
+            .any(|removed| {
+                let kept = (0..n)
+                    .filter(|i| removed & (1 << i) == 0)
+                    .map(|i| report[i])
+                    .collect::<Vec<_>>();
+                let steps = kept.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+                steps.iter().all(|d| (1..=3).contains(d))
+                    || steps.iter().all(|d| (-3..=-1).contains(d))
+            })
+    }
+
+    #[test]
+    fn remove_levels_matches_brute_force() {
+        let mut rng = crate::fuzz::Rng::new(2);
+        for _ in 0..2000 {
+            let report = (0..rng.below(9))
+                .map(|_| rng.below(12) as i64)
+                .collect::<Vec<_>>();
+            let max_removals = rng.below(4);
+            assert_eq!(
+                is_safe(&report, &SafetyPolicy::default(), max_removals),
+                is_safe_brute_force(&report, max_removals),
+                "{:?} with up to {} removals",
+                report,
+                max_removals
+            );
+        }
+    }
+
+    #[test]
+    fn dampener_matches_dynamic_programming() {
+        let policy = SafetyPolicy::default();
+        let dynamic_programming = |report: &[i64]| {
+            policy
+                .levels()
+                .iter()
+                .any(|levels| min_removals(report, &policy, levels, 1).is_some())
+        };
+        for report in [
+            &[9, 1, 2, 3][..],
+            &[1, 2, 3, 9],
+            &[1, 2, 9, 3],
+            &[1, 9, 2, 9, 3],
+            &[3, 2, 4, 5],
+            &[5, 5, 5],
+        ] {
+            assert_eq!(
+                is_safe(report, &policy, 1),
+                dynamic_programming(report),
+                "{:?}",
+                report
+            );
+        }
+    }
+
+    /// Generates an increasing report with `bad_levels` randomly placed
+    /// outliers.
+    fn generate_report(rng: &mut crate::fuzz::Rng, len: usize, bad_levels: usize) -> Vec<i64> {
+        let mut level = 0;
+        let mut report = (0..len)
+            .map(|_| {
+                level += 1 + rng.below(3) as i64;
+                level
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..bad_levels {
+            let i = rng.below(len);
+            report[i] = rng.below(4 * len) as i64;
+        }
+        report
+    }
+
+    /// Compares the dampener with the dynamic programming solution, run with
+    /// `cargo test --release bench_dampener -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_dampener() {
+        let mut rng = crate::fuzz::Rng::new(3);
+        let input = (0..200)
+            .map(|i| generate_report(&mut rng, 100_000, i % 3))
+            .collect::<Vec<_>>();
+        let policy = SafetyPolicy::default();
+
+        let start = std::time::Instant::now();
+        let dampener = count_safe(&input, &policy, 1);
+        let dampener_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let dynamic_programming = input
+            .iter()
+            .filter(|report| {
+                policy
+                    .levels()
+                    .iter()
+                    .any(|levels| min_removals(report, &policy, levels, 1).is_some())
+            })
+            .count();
+        let dynamic_programming_time = start.elapsed();
+
+        assert_eq!(dampener, dynamic_programming);
+        println!(
+            "dampener: {:?}, dynamic programming: {:?}",
+            dampener_time, dynamic_programming_time
+        );
+    }
+
+    #[test]
+    fn custom_policies() {
+        let input = parse(EXAMPLE).unwrap();
+        let default = SafetyPolicy::default();
+        let policy = |policy| part1_with_policy(&input, &policy);
+
+        let wide_steps = SafetyPolicy {
+            max_step: 5,
+            ..default
+        };
+        assert_eq!(policy(wide_steps), "4");
+        let plateaus = SafetyPolicy {
+            allow_plateaus: true,
+            ..default
+        };
+        assert_eq!(policy(plateaus), "3");
+        let increasing = SafetyPolicy {
+            direction: Direction::Increasing,
+            ..default
+        };
+        assert_eq!(policy(increasing), "1");
+        assert_eq!(part2_with_policy(&input, &increasing), "2");
+
+        let unordered = SafetyPolicy {
+            monotonic: false,
+            ..default
+        };
+        assert!(is_safe(&[1, 3, 2, 4], &unordered, 0));
+        assert!(!is_safe(&[1, 3, 2, 4], &default, 0));
+        assert!(!is_safe(&[1, 1, 2], &unordered, 0));
+        assert!(!is_safe(&[i64::MIN, i64::MAX], &unordered, 0));
+
+        let zero_steps = SafetyPolicy {
+            min_step: 0,
+            ..default
+        };
+        assert!(is_safe(&[1, 1, 2], &zero_steps, 0));
+        assert!(!is_safe(&[1, 1, 2], &default, 0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "min_step 4 exceeds max_step 3")]
+    fn reject_empty_step_range() {
+        let policy = SafetyPolicy {
+            min_step: 4,
+            ..SafetyPolicy::default()
+        };
+        is_safe(&[1, 5], &policy, 0);
+    }
+
+    #[test]
+    fn explain_example() {
+        assert_eq!(
+            explain(&parse(EXAMPLE).unwrap()),
+            "report 1: safe, decreasing\n\
+             report 2: unsafe, first unsafe step at level 1: 2 -> 7\n\
+             report 3: unsafe, first unsafe step at level 2: 6 -> 2\n\
+             report 4: safe without level 1, first unsafe step at level 1: 3 -> 2\n\
+             report 5: safe without level 2, first unsafe step at level 2: 4 -> 4\n\
+             report 6: safe, increasing"
+        );
+    }
+
+    #[test]
+    fn diagnose_matches_is_safe() {
+        let mut rng = crate::fuzz::Rng::new(4);
+        let policy = SafetyPolicy::default();
+        for _ in 0..2000 {
+            let report = (0..rng.below(8))
+                .map(|_| rng.below(10) as i64)
+                .collect::<Vec<_>>();
+            let diagnosis = diagnose(&report, &policy);
+            match diagnosis.classification {
+                Classification::SafeWithoutLevel(i) => {
+                    let mut without_level = report.clone();
+                    without_level.remove(i);
+                    assert!(is_safe(&without_level, &policy, 0));
+                }
+                Classification::Unsafe => assert!(!is_safe(&report, &policy, 1)),
+                _ => assert!(is_safe(&report, &policy, 0)),
+            }
+        }
+    }
+
+    #[test]
+    fn classify_stream() {
+        let mut classifier = classify(EXAMPLE.as_bytes(), SafetyPolicy::default());
+        let diagnosis = classifier.next().unwrap().unwrap();
+        assert_eq!(diagnosis.classification, Classification::SafeDecreasing);
+        assert_eq!(classifier.statistics().reports, 1);
+
+        assert_eq!(classifier.by_ref().filter(|d| d.is_ok()).count(), 5);
+        let statistics = classifier.statistics();
+        assert_eq!((statistics.safe, statistics.dampened), (4, 2));
+        assert_eq!(statistics.safe_ratio(), 4.0 / 6.0);
+        assert_eq!(
+            statistics.to_string(),
+            "reports: 6\n\
+             safe: 4 (66.7%, 2 after removing a level)\n\
+             report lengths: 5 (6x)\n\
+             failing steps: -4 (1x), -1 (1x), 0 (1x), 5 (1x)"
+        );
+    }
+
+    #[test]
+    fn classify_stream_errors() {
+        let mut classifier = classify("1 2 3\n1 x 3\n3 2 1".as_bytes(), SafetyPolicy::default());
+        assert!(classifier.next().unwrap().is_ok());
+        match classifier.next().unwrap() {
+            Err(e @ StreamError::Parse(_)) => assert_eq!(e.to_string(), "line 2: not a number"),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(classifier.next().unwrap().is_ok());
+        assert!(classifier.next().is_none());
+        assert_eq!(classifier.statistics().reports, 2);
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day2", EXAMPLE, |input| {
+            if let Ok(input) = parse(input) {
+                part1(&input);
+                part2(&input);
+            }
+        });
     }
 }
@@ -1,180 +1,601 @@
+use std::{
+    fmt,
+    io::{self, Read},
+    ops::Range,
+};
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[aoc_generator(day3)]
-fn parse(input: &str) -> String {
-    input.to_string()
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Mul(u64, u64),
+    Do,
+    Dont,
+    /// An instruction which is not part of the puzzle, executed by the
+    /// [`InstructionSpec`] of the same name.
+    Custom {
+        name: &'static str,
+        operands: [u64; MAX_ARITY],
+    },
 }
 
-#[aoc(day3, part1)]
-fn part1(input: &str) -> String {
-    fn try_eval(program: &str, offset: usize) -> Option<u64> {
-        enum State {
-            Init,
-            MulCharM,
-            MulCharU,
-            MulCharL,
-            MulNumberA,
-            MulNumberB,
-        }
-
-        let mut state = State::Init;
-        let mut reg_number_start = 0;
-        let mut reg_number_a = 0;
-
-        for (i, c) in program.as_bytes().iter().enumerate().skip(offset) {
-            let c = char::from(*c);
-            match state {
-                State::Init if c == 'm' => {
-                    state = State::MulCharM;
-                }
-                State::MulCharM if c == 'u' => {
-                    state = State::MulCharU;
-                }
-                State::MulCharU if c == 'l' => {
-                    state = State::MulCharL;
-                }
-                State::MulCharL if c == '(' => {
-                    state = State::MulNumberA;
-                    reg_number_start = i + 1;
-                }
-                State::MulNumberA => {
-                    if c == ',' {
-                        if let Ok(number_a) = program[reg_number_start..i].parse::<u64>() {
-                            state = State::MulNumberB;
-                            reg_number_start = i + 1;
-                            reg_number_a = number_a;
-                        } else {
-                            break;
-                        }
-                    }
-                }
-                State::MulNumberB => {
-                    if c == ')' {
-                        return if let Ok(number_b) = program[reg_number_start..i].parse::<u64>() {
-                            Some(reg_number_a * number_b)
-                        } else {
-                            None
-                        };
-                    }
-                }
-                _ => break,
-            }
+impl Instruction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instruction::Mul(..) => MUL.name,
+            Instruction::Do => DO.name,
+            Instruction::Dont => DONT.name,
+            Instruction::Custom { name, .. } => name,
         }
+    }
 
-        None
+    /// The operands, padded with zeros.
+    pub fn operands(&self) -> [u64; MAX_ARITY] {
+        match *self {
+            Instruction::Mul(a, b) => [a, b],
+            Instruction::Do | Instruction::Dont => [0; MAX_ARITY],
+            Instruction::Custom { operands, .. } => operands,
+        }
     }
+}
 
-    input
-        .char_indices()
-        .filter_map(|(i, _)| try_eval(input, i))
-        .sum::<u64>()
-        .to_string()
+/// An instruction found in the corrupted memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub instruction: Instruction,
+    /// Byte offsets of the instruction in the memory.
+    pub span: Range<usize>,
 }
 
-#[aoc(day3, part2)]
-fn part2(input: &str) -> String {
-    enum Token {
-        Do,
-        DoNot,
-        Mul(u64),
-    }
-
-    fn try_eval(program: &str, offset: usize) -> Option<Token> {
-        enum State {
-            DoCharD,
-            DoCharO,
-            DontCharN,
-            DontCharApostrophe,
-            Init,
-            MulCharM,
-            MulCharU,
-            MulCharL,
-            MulNumberA,
-            MulNumberB,
-        }
-
-        let mut state = State::Init;
-        let mut reg_number_start = 0;
-        let mut reg_number_a = 0;
-
-        for (i, c) in program.as_bytes().iter().enumerate().skip(offset) {
-            let c = char::from(*c);
-            match state {
-                State::Init if c == 'm' => {
-                    state = State::MulCharM;
-                }
-                State::MulCharM if c == 'u' => {
-                    state = State::MulCharU;
+/// Syntax and semantics of an instruction, `name(a,b,..)` with `arity`
+/// operands.
+pub struct InstructionSpec {
+    pub name: &'static str,
+    pub arity: usize,
+    /// Builds the instruction from its operands, `None` rejects them.
+    pub build: fn([u64; MAX_ARITY]) -> Option<Instruction>,
+    /// Executes the instruction built from the operands.
+    pub execute: fn(&mut Machine, [u64; MAX_ARITY]),
+}
+
+pub const MUL: InstructionSpec = InstructionSpec {
+    name: "mul",
+    arity: 2,
+    build: |[a, b]| a.checked_mul(b).map(|_| Instruction::Mul(a, b)),
+    execute: |machine, [a, b]| machine.accumulate(i128::from(a) * i128::from(b)),
+};
+
+pub const DO: InstructionSpec = InstructionSpec {
+    name: "do",
+    arity: 0,
+    build: |_| Some(Instruction::Do),
+    execute: |machine, _| machine.enabled = true,
+};
+
+pub const DONT: InstructionSpec = InstructionSpec {
+    name: "don't",
+    arity: 0,
+    build: |_| Some(Instruction::Dont),
+    execute: |machine, _| machine.enabled = false,
+};
+
+/// The instructions recognized in part 1.
+pub const PART1_INSTRUCTIONS: &[InstructionSpec] = &[MUL];
+
+/// The instructions recognized in part 2.
+pub const PART2_INSTRUCTIONS: &[InstructionSpec] = &[MUL, DO, DONT];
+
+pub const MAX_ARITY: usize = 2;
+
+/// Which operands are accepted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Grammar {
+    /// One to three ASCII digits, as in the puzzle text.
+    #[default]
+    Strict,
+    /// Anything `str::parse::<u64>` accepts, i.e. an optional `+` followed by
+    /// any number of digits as long as the value fits into a `u64`.
+    Lenient,
+}
+
+/// Number of digits an operand may have in [`Grammar::Strict`].
+const STRICT_MAX_DIGITS: usize = 3;
+
+enum State {
+    Init,
+    /// The first `matched` bytes of the name of `instructions[spec]` were
+    /// matched.
+    Name {
+        spec: usize,
+        matched: usize,
+    },
+    /// Within the parentheses of `instructions[spec]`, `operands[..count]`
+    /// are complete.
+    Operands {
+        spec: usize,
+        operands: [u64; MAX_ARITY],
+        count: usize,
+        operand: Operand,
+    },
+}
+
+/// The operand currently being read.
+#[derive(Clone, Copy, Default)]
+struct Operand {
+    value: u64,
+    digits: usize,
+    /// Whether the operand started with a `+`, which `str::parse` accepts.
+    sign: bool,
+}
+
+/// Finds instructions in the corrupted memory in a single pass, one byte at a
+/// time.
+///
+/// A byte which does not continue the current instruction may start a new
+/// one, and so may the bytes of a partially matched name, which are replayed.
+/// Names contain no parentheses or operand bytes, and no name ends with
+/// another one, so no other instruction can start within the current one.
+pub struct Lexer<'a> {
+    instructions: &'a [InstructionSpec],
+    grammar: Grammar,
+    state: State,
+    start: usize,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer recognizing `instructions`. Their names must consist
+    /// of bytes other than parentheses, commas, `+` and digits, and must not
+    /// end with the name of another instruction.
+    pub fn new(instructions: &'a [InstructionSpec], grammar: Grammar) -> Self {
+        debug_assert!(instructions.iter().all(|i| {
+            !i.name.is_empty()
+                && i.arity <= MAX_ARITY
+                && !i
+                    .name
+                    .bytes()
+                    .any(|b| b.is_ascii_digit() || b"(),+".contains(&b))
+                && instructions
+                    .iter()
+                    .all(|j| j.name.len() >= i.name.len() || !i.name.ends_with(j.name))
+        }));
+        Lexer {
+            instructions,
+            grammar,
+            state: State::Init,
+            start: 0,
+            position: 0,
+        }
+    }
+
+    /// Consumes the next byte, returns the instruction it completes.
+    pub fn push(&mut self, byte: u8) -> Option<Token> {
+        let token = self.advance(byte, self.position);
+        self.position += 1;
+        token
+    }
+
+    fn advance(&mut self, byte: u8, position: usize) -> Option<Token> {
+        match self.step(byte, position) {
+            Ok(token) => token,
+            Err(()) => {
+                let state = std::mem::replace(&mut self.state, State::Init);
+                if let State::Name { spec, matched } = state {
+                    // another instruction may start within the matched name,
+                    // which contains no `)` and thus completes no instruction
+                    let start = self.start;
+                    let name = &self.instructions[spec].name.as_bytes()[..matched];
+                    for (offset, &b) in name.iter().enumerate().skip(1) {
+                        self.advance(b, start + offset);
+                    }
                 }
-                State::MulCharU if c == 'l' => {
-                    state = State::MulCharL;
+                self.advance(byte, position)
+            }
+        }
+    }
+
+    /// Advances the state machine, `Err` means that `byte` does not continue
+    /// the current instruction.
+    fn step(&mut self, byte: u8, position: usize) -> Result<Option<Token>, ()> {
+        match self.state {
+            State::Init => {
+                if let Some(spec) = self
+                    .instructions
+                    .iter()
+                    .position(|i| i.name.as_bytes()[0] == byte)
+                {
+                    self.start = position;
+                    self.state = State::Name { spec, matched: 1 };
                 }
-                State::MulCharL if c == '(' => {
-                    state = State::MulNumberA;
-                    reg_number_start = i + 1;
+                Ok(None)
+            }
+            State::Name { spec, matched } => {
+                let prefix = &self.instructions[spec].name.as_bytes()[..matched];
+                if let Some(spec) = self.instructions.iter().position(|i| {
+                    let name = i.name.as_bytes();
+                    name.len() > matched && name.starts_with(prefix) && name[matched] == byte
+                }) {
+                    self.state = State::Name {
+                        spec,
+                        matched: matched + 1,
+                    };
+                    Ok(None)
+                } else if byte == b'(' {
+                    let spec = self
+                        .instructions
+                        .iter()
+                        .position(|i| i.name.as_bytes() == prefix)
+                        .ok_or(())?;
+                    self.state = State::Operands {
+                        spec,
+                        operands: [0; MAX_ARITY],
+                        count: 0,
+                        operand: Operand::default(),
+                    };
+                    Ok(None)
+                } else {
+                    Err(())
                 }
-                State::MulNumberA => {
-                    if c == ',' {
-                        if let Ok(number_a) = program[reg_number_start..i].parse::<u64>() {
-                            state = State::MulNumberB;
-                            reg_number_start = i + 1;
-                            reg_number_a = number_a;
-                        } else {
-                            break;
+            }
+            State::Operands {
+                spec,
+                ref mut operands,
+                ref mut count,
+                ref mut operand,
+            } => {
+                let arity = self.instructions[spec].arity;
+                match byte {
+                    b'0'..=b'9' if *count < arity => {
+                        if self.grammar == Grammar::Strict && operand.digits == STRICT_MAX_DIGITS {
+                            return Err(());
                         }
+                        operand.value = operand
+                            .value
+                            .checked_mul(10)
+                            .and_then(|v| v.checked_add(u64::from(byte - b'0')))
+                            .ok_or(())?;
+                        operand.digits += 1;
+                        Ok(None)
                     }
-                }
-                State::MulNumberB => {
-                    if c == ')' {
-                        return if let Ok(number_b) = program[reg_number_start..i].parse::<u64>() {
-                            Some(Token::Mul(reg_number_a * number_b))
-                        } else {
-                            None
-                        };
+                    b'+' if self.grammar == Grammar::Lenient
+                        && *count < arity
+                        && operand.digits == 0
+                        && !operand.sign =>
+                    {
+                        operand.sign = true;
+                        Ok(None)
                     }
-                }
-                State::Init if c == 'd' => {
-                    state = State::DoCharD;
-                }
-                State::DoCharD if c == 'o' => {
-                    state = State::DoCharO;
-                }
-                State::DoCharO => {
-                    if c == 'n' {
-                        state = State::DontCharN;
-                    } else {
-                        return Some(Token::Do);
+                    b',' if *count + 1 < arity && operand.digits > 0 => {
+                        operands[*count] = operand.value;
+                        *count += 1;
+                        *operand = Operand::default();
+                        Ok(None)
                     }
+                    b')' if (arity == 0 && operand.digits == 0)
+                        || (*count + 1 == arity && operand.digits > 0) =>
+                    {
+                        if arity > 0 {
+                            operands[*count] = operand.value;
+                        }
+                        let operands = *operands;
+                        self.emit(spec, operands, position).map(Some).ok_or(())
+                    }
+                    _ => Err(()),
                 }
-                State::DontCharN if c == '\'' => {
-                    state = State::DontCharApostrophe;
-                }
-                State::DontCharApostrophe if c == 't' => {
-                    return Some(Token::DoNot);
-                }
-                _ => break,
             }
         }
+    }
+
+    fn emit(&mut self, spec: usize, operands: [u64; MAX_ARITY], position: usize) -> Option<Token> {
+        let instruction = (self.instructions[spec].build)(operands)?;
+        self.state = State::Init;
+        Some(Token {
+            instruction,
+            span: self.start..position + 1,
+        })
+    }
+}
+
+/// Returns all `instructions` in `memory` in order. The memory may contain
+/// arbitrary bytes, it need not be UTF-8.
+pub fn tokenize<'a>(
+    memory: &'a [u8],
+    instructions: &'a [InstructionSpec],
+    grammar: Grammar,
+) -> impl Iterator<Item = Token> + 'a {
+    let mut lexer = Lexer::new(instructions, grammar);
+    memory.iter().filter_map(move |&byte| lexer.push(byte))
+}
+
+/// State of the machine running the instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Machine {
+    /// Whether instructions changing the accumulator take effect.
+    pub enabled: bool,
+    pub accumulator: i128,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Machine {
+            enabled: true,
+            accumulator: 0,
+        }
+    }
+}
+
+impl Machine {
+    /// Adds `value` to the accumulator, unless the machine is disabled.
+    pub fn accumulate(&mut self, value: i128) {
+        if self.enabled {
+            self.accumulator += value;
+        }
+    }
+}
+
+/// A language of instructions, which are parsed from the corrupted memory and
+/// run on a [`Machine`].
+#[derive(Clone, Copy)]
+pub struct Interpreter<'a> {
+    instructions: &'a [InstructionSpec],
+    grammar: Grammar,
+}
+
+/// The language of part 1.
+pub const PART1: Interpreter = Interpreter::new(PART1_INSTRUCTIONS, Grammar::Strict);
+
+/// The language of part 2.
+pub const PART2: Interpreter = Interpreter::new(PART2_INSTRUCTIONS, Grammar::Strict);
+
+impl<'a> Interpreter<'a> {
+    pub const fn new(instructions: &'a [InstructionSpec], grammar: Grammar) -> Self {
+        Interpreter {
+            instructions,
+            grammar,
+        }
+    }
+
+    /// Returns the instructions of the language in `memory` in order.
+    pub fn tokenize<'m>(&self, memory: &'m [u8]) -> impl Iterator<Item = Token> + 'm
+    where
+        'a: 'm,
+    {
+        tokenize(memory, self.instructions, self.grammar)
+    }
+
+    /// Returns the program hidden in `memory`.
+    pub fn parse(&self, memory: &[u8]) -> Vec<Instruction> {
+        self.tokenize(memory)
+            .map(|token| token.instruction)
+            .collect()
+    }
+
+    /// Executes a single instruction, ignoring instructions which are not
+    /// part of the language.
+    pub fn execute(&self, machine: &mut Machine, instruction: &Instruction) {
+        if let Some(spec) = self
+            .instructions
+            .iter()
+            .find(|spec| spec.name == instruction.name())
+        {
+            (spec.execute)(machine, instruction.operands());
+        }
+    }
+
+    /// Runs `program` on a fresh machine and returns its final state.
+    pub fn run(&self, program: &[Instruction]) -> Machine {
+        let mut machine = Machine::default();
+        for instruction in program {
+            self.execute(&mut machine, instruction);
+        }
+        machine
+    }
+
+    /// Runs the program in the memory read from `reader`, without holding
+    /// all of it in memory.
+    pub fn run_reader(&self, mut reader: impl Read) -> io::Result<Machine> {
+        let mut evaluator = Evaluator::new(*self);
+        let mut buffer = [0; 8192];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(evaluator.machine()),
+                Ok(read) => evaluator.feed(&buffer[..read]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Runs the program in memory arriving in chunks of any size. An instruction
+/// split across chunks is still found, as the lexer keeps its state.
+pub struct Evaluator<'a> {
+    interpreter: Interpreter<'a>,
+    lexer: Lexer<'a>,
+    machine: Machine,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(interpreter: Interpreter<'a>) -> Self {
+        Evaluator {
+            interpreter,
+            lexer: Lexer::new(interpreter.instructions, interpreter.grammar),
+            machine: Machine::default(),
+        }
+    }
+
+    /// Executes the instructions completed by the next `chunk` of memory.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            if let Some(token) = self.lexer.push(byte) {
+                self.interpreter
+                    .execute(&mut self.machine, &token.instruction);
+            }
+        }
+    }
+
+    /// The state after the memory fed so far.
+    pub fn machine(&self) -> Machine {
+        self.machine
+    }
+}
+
+/// How [`render`] marks the instructions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Highlight {
+    /// Green for accepted instructions, red for disabled ones and yellow for
+    /// `do()` and `don't()`.
+    #[default]
+    Ansi,
+    /// `[..]` around accepted instructions, `{..}` around disabled ones and
+    /// `<..>` around `do()` and `don't()`.
+    Plain,
+}
+
+impl Highlight {
+    fn delimiters(self, instruction: &Instruction, enabled: bool) -> (&'static str, &'static str) {
+        let toggle = matches!(instruction, Instruction::Do | Instruction::Dont);
+        match self {
+            Highlight::Ansi if toggle => ("\x1b[33m", "\x1b[0m"),
+            Highlight::Ansi if enabled => ("\x1b[32m", "\x1b[0m"),
+            Highlight::Ansi => ("\x1b[31m", "\x1b[0m"),
+            Highlight::Plain if toggle => ("<", ">"),
+            Highlight::Plain if enabled => ("[", "]"),
+            Highlight::Plain => ("{", "}"),
+        }
+    }
+}
+
+/// Returns `memory` with the instructions `interpreter` finds highlighted,
+/// depending on whether the machine was enabled when executing them. Invalid
+/// UTF-8 is replaced by U+FFFD.
+pub fn render(memory: &[u8], interpreter: &Interpreter, highlight: Highlight) -> String {
+    let mut machine = Machine::default();
+    let mut rendered = String::with_capacity(memory.len());
+    let mut end = 0;
+    for token in interpreter.tokenize(memory) {
+        let (open, close) = highlight.delimiters(&token.instruction, machine.enabled);
+        interpreter.execute(&mut machine, &token.instruction);
+        rendered.push_str(&String::from_utf8_lossy(&memory[end..token.span.start]));
+        rendered.push_str(open);
+        rendered.push_str(&String::from_utf8_lossy(&memory[token.span.clone()]));
+        rendered.push_str(close);
+        end = token.span.end;
+    }
+    rendered.push_str(&String::from_utf8_lossy(&memory[end..]));
+    rendered
+}
 
-        None
+/// A part of the memory from one `do()` or `don't()` up to the next one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    /// Byte offsets of the region, which starts with its `do()` or `don't()`
+    /// unless it is the first one.
+    pub span: Range<usize>,
+    pub enabled: bool,
+    pub muls: usize,
+    /// Sum of the products of all `mul` instructions in the region, whether
+    /// it is enabled or not.
+    pub subtotal: u128,
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bytes {}..{}, {}, {} mul{}, subtotal {}",
+            self.span.start,
+            self.span.end,
+            if self.enabled { "enabled" } else { "disabled" },
+            self.muls,
+            if self.muls == 1 { "" } else { "s" },
+            self.subtotal
+        )
     }
+}
 
-    let mut sum = 0;
-    let mut enabled = true;
-    for (i, _) in input.char_indices() {
-        match try_eval(input, i) {
-            Some(Token::Do) => enabled = true,
-            Some(Token::DoNot) => enabled = false,
-            Some(Token::Mul(v)) => {
-                if enabled {
-                    sum += v
+/// Splits `memory` into the regions between the `do()` and `don't()`
+/// instructions `interpreter` finds.
+pub fn regions(memory: &[u8], interpreter: &Interpreter) -> Vec<Region> {
+    let mut machine = Machine::default();
+    let mut regions = vec![Region {
+        span: 0..memory.len(),
+        enabled: machine.enabled,
+        muls: 0,
+        subtotal: 0,
+    }];
+    for token in interpreter.tokenize(memory) {
+        interpreter.execute(&mut machine, &token.instruction);
+        let region = regions.last_mut().expect("there is always a region");
+        match token.instruction {
+            Instruction::Do | Instruction::Dont => {
+                region.span.end = token.span.start;
+                if region.span.is_empty() {
+                    // the memory starts with a toggle
+                    regions.pop();
                 }
+                regions.push(Region {
+                    span: token.span.start..memory.len(),
+                    enabled: machine.enabled,
+                    muls: 0,
+                    subtotal: 0,
+                });
+            }
+            Instruction::Mul(a, b) => {
+                region.muls += 1;
+                region.subtotal += u128::from(a) * u128::from(b);
             }
-            None => (),
+            Instruction::Custom { .. } => (),
         }
     }
+    regions
+}
+
+#[aoc_generator(day3)]
+pub fn parse(input: &str) -> String {
+    input.to_string()
+}
+
+#[aoc(day3, part1)]
+pub fn part1(input: &str) -> String {
+    PART1
+        .run(&PART1.parse(input.as_bytes()))
+        .accumulator
+        .to_string()
+}
 
-    sum.to_string()
+#[aoc(day3, part2)]
+pub fn part2(input: &str) -> String {
+    PART2
+        .run(&PART2.parse(input.as_bytes()))
+        .accumulator
+        .to_string()
+}
+
+/// Lists the enabled and disabled regions of the memory in part 2.
+pub fn explain(input: &str) -> String {
+    let regions = regions(input.as_bytes(), &PART2);
+    let total: u128 = regions
+        .iter()
+        .filter(|region| region.enabled)
+        .map(|region| region.subtotal)
+        .sum();
+    regions
+        .iter()
+        .enumerate()
+        .map(|(i, region)| format!("region {}: {}", i + 1, region))
+        .chain([format!("enabled total: {}", total)])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Summarizes the size of the corrupted memory.
+pub fn describe(input: &str) -> String {
+    format!(
+        "memory: {} bytes in {} lines",
+        input.len(),
+        input.lines().count()
+    )
 }
 
 #[cfg(test)]
@@ -192,4 +613,402 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse(EXAMPLE)), "48");
     }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(describe(&parse(EXAMPLE)), "memory: 74 bytes in 1 lines");
+    }
+
+    #[test]
+    fn explain_example() {
+        assert_eq!(
+            explain(&parse(EXAMPLE)),
+            "region 1: bytes 0..20, enabled, 1 mul, subtotal 8\n\
+             region 2: bytes 20..59, disabled, 2 muls, subtotal 113\n\
+             region 3: bytes 59..74, enabled, 1 mul, subtotal 40\n\
+             enabled total: 48"
+        );
+    }
+
+    #[test]
+    fn regions_cover_memory() {
+        let memory = b"don't()mul(1,2)don't()do()mul(3,4)";
+        assert_eq!(
+            regions(memory, &PART2),
+            [
+                Region {
+                    span: 0..15,
+                    enabled: false,
+                    muls: 1,
+                    subtotal: 2
+                },
+                Region {
+                    span: 15..22,
+                    enabled: false,
+                    muls: 0,
+                    subtotal: 0
+                },
+                Region {
+                    span: 22..34,
+                    enabled: true,
+                    muls: 1,
+                    subtotal: 12
+                },
+            ]
+        );
+        assert_eq!(
+            regions(b"mul(1,2)", &PART1),
+            [Region {
+                span: 0..8,
+                enabled: true,
+                muls: 1,
+                subtotal: 2
+            }]
+        );
+        assert_eq!(regions(b"", &PART2).len(), 1);
+
+        const UNCHECKED_MUL: InstructionSpec = InstructionSpec {
+            build: |[a, b]| Some(Instruction::Mul(a, b)),
+            ..MUL
+        };
+        let interpreter = Interpreter::new(&[UNCHECKED_MUL], Grammar::Lenient);
+        assert_eq!(
+            regions(b"mul(18446744073709551615,2)", &interpreter)[0].subtotal,
+            2 * u128::from(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn tokenize_example() {
+        let tokens =
+            tokenize(EXAMPLE.as_bytes(), PART2_INSTRUCTIONS, Grammar::Strict).collect::<Vec<_>>();
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(
+            tokens[0],
+            Token {
+                instruction: Instruction::Mul(2, 4),
+                span: 1..9
+            }
+        );
+        assert_eq!(&EXAMPLE[tokens[1].span.clone()], "don't()");
+        assert_eq!(&EXAMPLE[tokens[4].span.clone()], "do()");
+    }
+
+    fn instructions(memory: &str, grammar: Grammar) -> Vec<Instruction> {
+        tokenize(memory.as_bytes(), PART2_INSTRUCTIONS, grammar)
+            .map(|token| token.instruction)
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_overlapping_candidates() {
+        let strict = |memory| instructions(memory, Grammar::Strict);
+        assert_eq!(strict("mmul(2,3)"), [Instruction::Mul(2, 3)]);
+        assert_eq!(strict("mul(2,mul(3,4))"), [Instruction::Mul(3, 4)]);
+        assert_eq!(
+            strict("dodo()don'tdon't()"),
+            [Instruction::Do, Instruction::Dont]
+        );
+        assert_eq!(strict("mul(5,3)mul(2,3"), [Instruction::Mul(5, 3)]);
+
+        assert!(
+            tokenize(b"do()mul(2,3)don't()", PART1_INSTRUCTIONS, Grammar::Strict)
+                .all(|token| matches!(token.instruction, Instruction::Mul(..)))
+        );
+    }
+
+    #[test]
+    fn strict_operands() {
+        let strict = |memory| instructions(memory, Grammar::Strict);
+        assert_eq!(strict("mul(0,0)"), [Instruction::Mul(0, 0)]);
+        assert_eq!(strict("mul(007,999)"), [Instruction::Mul(7, 999)]);
+        assert_eq!(strict("mul(1234,5)"), []);
+        assert_eq!(strict("mul(5,1234)"), []);
+        assert_eq!(strict("mul(+5,3)"), []);
+        assert_eq!(strict("mul(,3)"), []);
+        assert_eq!(strict("mul(3,)"), []);
+        assert_eq!(strict("mul( 1,2)"), []);
+        assert_eq!(strict("mul(1,2,3)"), []);
+        assert_eq!(strict("mul(-1,2)"), []);
+        assert_eq!(strict("mul(1234mul(5,6)"), [Instruction::Mul(5, 6)]);
+    }
+
+    #[test]
+    fn lenient_operands() {
+        let lenient = |memory| instructions(memory, Grammar::Lenient);
+        assert_eq!(lenient("mul(+5,3)"), [Instruction::Mul(5, 3)]);
+        assert_eq!(lenient("mul(1234,5)"), [Instruction::Mul(1234, 5)]);
+        assert_eq!(lenient("mul(++5,3)"), []);
+        assert_eq!(lenient("mul(5+,3)"), []);
+        assert_eq!(lenient("mul(+,3)"), []);
+        assert_eq!(
+            lenient("mul(18446744073709551615,1)"),
+            [Instruction::Mul(u64::MAX, 1)]
+        );
+        assert_eq!(lenient("mul(18446744073709551616,1)"), []);
+        assert_eq!(
+            lenient("mul(4294967295,4294967297)"),
+            [Instruction::Mul(4294967295, 4294967297)]
+        );
+        assert_eq!(lenient("mul(4294967296,4294967296)"), []);
+        let interpreter = Interpreter::new(PART1_INSTRUCTIONS, Grammar::Lenient);
+        let program = interpreter.parse(b"mul(18446744073709551615,1)mul(18446744073709551615,1)");
+        assert_eq!(
+            interpreter.run(&program).accumulator,
+            2 * i128::from(u64::MAX)
+        );
+    }
+
+    const ADD: InstructionSpec = InstructionSpec {
+        name: "add",
+        arity: 2,
+        build: |operands| {
+            Some(Instruction::Custom {
+                name: "add",
+                operands,
+            })
+        },
+        execute: |machine, [a, b]| machine.accumulate(i128::from(a) + i128::from(b)),
+    };
+
+    const SUB: InstructionSpec = InstructionSpec {
+        name: "sub",
+        arity: 2,
+        build: |operands| {
+            Some(Instruction::Custom {
+                name: "sub",
+                operands,
+            })
+        },
+        execute: |machine, [a, b]| machine.accumulate(i128::from(a) - i128::from(b)),
+    };
+
+    const EXTENDED_INSTRUCTIONS: &[InstructionSpec] = &[MUL, DO, DONT, ADD, SUB];
+
+    #[test]
+    fn interpret_custom_instructions() {
+        let interpreter = Interpreter::new(EXTENDED_INSTRUCTIONS, Grammar::Strict);
+        let program = interpreter.parse(b"xadd(2,3)sub(10,4)don't()add(1,1)do()mul(2,2)sub(9,x)");
+        assert_eq!(
+            program,
+            [
+                Instruction::Custom {
+                    name: "add",
+                    operands: [2, 3]
+                },
+                Instruction::Custom {
+                    name: "sub",
+                    operands: [10, 4]
+                },
+                Instruction::Dont,
+                Instruction::Custom {
+                    name: "add",
+                    operands: [1, 1]
+                },
+                Instruction::Do,
+                Instruction::Mul(2, 2),
+            ]
+        );
+        assert_eq!(
+            interpreter.run(&program),
+            Machine {
+                enabled: true,
+                accumulator: 5 + 6 + 4
+            }
+        );
+        assert_eq!(
+            interpreter.run(&interpreter.parse(b"sub(1,5)")).accumulator,
+            -4
+        );
+
+        // the part 2 language ignores the custom instructions
+        assert_eq!(PART2.run(&program).accumulator, 4);
+
+        // instructions built directly skip the checks of `build`
+        assert_eq!(
+            PART1.run(&[Instruction::Mul(u64::MAX, 2)]).accumulator,
+            2 * i128::from(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn tokenize_within_partial_names() {
+        let names = |memory: &str| {
+            tokenize(memory.as_bytes(), EXTENDED_INSTRUCTIONS, Grammar::Strict)
+                .map(|token| (token.instruction.name(), token.span))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("addo()"), [("do", 2..6)]);
+        assert_eq!(names("adddon't()"), [("don't", 3..10)]);
+        assert_eq!(names("aadd(1,2)"), [("add", 1..9)]);
+    }
+
+    #[test]
+    fn render_example() {
+        assert_eq!(
+            render(EXAMPLE.as_bytes(), &PART2, Highlight::Plain),
+            "x[mul(2,4)]&mul[3,7]!^<don't()>_{mul(5,5)}+mul(32,64]({mul(11,8)}un<do()>?[mul(8,5)])\n"
+        );
+        assert_eq!(
+            render(EXAMPLE.as_bytes(), &PART1, Highlight::Plain),
+            "x[mul(2,4)]&mul[3,7]!^don't()_[mul(5,5)]+mul(32,64]([mul(11,8)]undo()?[mul(8,5)])\n"
+        );
+        assert_eq!(
+            render(b"a mul(1,2) don't() mul(3,4)", &PART2, Highlight::Ansi),
+            "a \x1b[32mmul(1,2)\x1b[0m \x1b[33mdon't()\x1b[0m \x1b[31mmul(3,4)\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn evaluate_split_instruction() {
+        let mut evaluator = Evaluator::new(PART2);
+        evaluator.feed(b"mul(12,");
+        assert_eq!(evaluator.machine().accumulator, 0);
+        evaluator.feed(b"3)don");
+        assert_eq!(evaluator.machine().accumulator, 36);
+        evaluator.feed(b"'t()mul(1,1)d");
+        evaluator.feed(b"o()mul(2,2)");
+        assert_eq!(
+            evaluator.machine(),
+            Machine {
+                enabled: true,
+                accumulator: 40
+            }
+        );
+    }
+
+    /// Returns reads of random length and is sometimes interrupted.
+    struct ChunkedReader<'a> {
+        memory: &'a [u8],
+        rng: crate::fuzz::Rng,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.rng.below(4) == 0 {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let len = self
+                .rng
+                .below(self.memory.len().min(buffer.len()) + 1)
+                .max(usize::from(!self.memory.is_empty()));
+            buffer[..len].copy_from_slice(&self.memory[..len]);
+            self.memory = &self.memory[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn run_reader_matches_run() {
+        let memory = EXAMPLE.repeat(20);
+        for seed in 0..200 {
+            for interpreter in [PART1, PART2] {
+                let reader = ChunkedReader {
+                    memory: memory.as_bytes(),
+                    rng: crate::fuzz::Rng::new(seed),
+                };
+                assert_eq!(
+                    interpreter.run_reader(reader).unwrap(),
+                    interpreter.run(&interpreter.parse(memory.as_bytes()))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn non_ascii_memory() {
+        let memory = "€mul(2,3)äöü😀mul(4,5)mul(€1,2)mul(1,2€)do\u{301}()don't()mul(6,6)";
+        assert_eq!(part1(memory), "62");
+        assert_eq!(part2(memory), "26");
+
+        let memory = b"\xffmul(2,\xc3)mul(3,3)\xe2\x82mul(1,\xe2\x82\xac1)\xc3mul(4,4)\x80";
+        assert_eq!(PART1.run(&PART1.parse(memory)).accumulator, 25);
+        assert_eq!(
+            render(memory, &PART1, Highlight::Plain),
+            "\u{fffd}mul(2,\u{fffd})[mul(3,3)]\u{fffd}mul(1,\u{20ac}1)\u{fffd}[mul(4,4)]\u{fffd}"
+        );
+    }
+
+    /// Tries to match an instruction at every offset.
+    fn tokenize_naive(memory: &[u8], instructions: &[InstructionSpec]) -> Vec<Token> {
+        let is_operand = |s: &[u8]| {
+            let digits = s.strip_prefix(b"+").unwrap_or(s);
+            !digits.is_empty() && digits.iter().all(|b| b.is_ascii_digit())
+        };
+        (0..memory.len())
+            .filter_map(|start| {
+                instructions.iter().find_map(|spec| {
+                    let (name, arity) = (spec.name, spec.arity);
+                    let rest = memory[start..]
+                        .strip_prefix(name.as_bytes())?
+                        .strip_prefix(b"(")?;
+                    let end = rest.iter().position(|&b| b == b')')?;
+                    let operands = match arity {
+                        0 if end == 0 => vec![],
+                        0 => return None,
+                        _ => rest[..end].split(|&b| b == b',').collect(),
+                    };
+                    if operands.len() != arity || !operands.iter().all(|o| is_operand(o)) {
+                        return None;
+                    }
+                    let mut values = [0; MAX_ARITY];
+                    for (value, operand) in values.iter_mut().zip(operands) {
+                        *value = std::str::from_utf8(operand).ok()?.parse().ok()?;
+                    }
+                    let instruction = (spec.build)(values)?;
+                    Some(Token {
+                        instruction,
+                        span: start..start + name.len() + end + 2,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_matches_naive() {
+        let mut rng = crate::fuzz::Rng::new(5);
+        let fragments: [&[u8]; 19] = [
+            b"mul(",
+            b"do",
+            b"don't",
+            b"()",
+            b"m",
+            b"1",
+            b"23",
+            b"+",
+            b",",
+            b")",
+            b"x",
+            b"add(",
+            b"a",
+            b"d",
+            b"sub(",
+            "€".as_bytes(),
+            "ä".as_bytes(),
+            b"\xff",
+            b"\xc3",
+        ];
+        for _ in 0..5000 {
+            let memory = (0..rng.below(16))
+                .flat_map(|_| fragments[rng.below(fragments.len())])
+                .copied()
+                .collect::<Vec<u8>>();
+            assert_eq!(
+                tokenize(&memory, EXTENDED_INSTRUCTIONS, Grammar::Lenient).collect::<Vec<_>>(),
+                tokenize_naive(&memory, EXTENDED_INSTRUCTIONS),
+                "{:?}",
+                String::from_utf8_lossy(&memory)
+            );
+        }
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day3", EXAMPLE, |input| {
+            let input = parse(input);
+            part1(&input);
+            part2(&input);
+        });
+    }
 }
@@ -19,38 +19,95 @@ fn search_word(
     )
 }
 
-#[aoc_generator(day4)]
-fn parse(input: &str) -> Vec<Vec<char>> {
-    input.lines().map(|line| line.chars().collect()).collect()
+/// One of the eight directions a word can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
 }
 
-#[aoc(day4, part1)]
-fn part1(input: &[Vec<char>]) -> String {
-    const WORD: [char; 4] = ['X', 'M', 'A', 'S'];
-    input
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The step as `(dy, dx)`, rows grow southwards.
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+/// A word found in the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match<'w> {
+    pub word: &'w str,
+    /// Position `(y, x)` of the first letter.
+    pub start: (usize, usize),
+    pub direction: Direction,
+}
+
+/// Finds every occurrence of `words` in `grid`, ordered by start position,
+/// then in the order of `words`, then by [`Direction::ALL`]. A word which
+/// reads the same in several directions, like a single letter, is found once
+/// per direction. Empty words are never found.
+pub fn find_words<'w>(grid: &[Vec<char>], words: &[&'w str]) -> Vec<Match<'w>> {
+    let words = words
         .iter()
+        .filter(|word| !word.is_empty())
+        .map(|word| (*word, word.chars().collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    grid.iter()
         .enumerate()
-        .flat_map(|(y, line)| {
-            line.iter().enumerate().flat_map(move |(x, _)| {
-                [
-                    search_word(input, &WORD, (y, x), (-1, -1)),
-                    search_word(input, &WORD, (y, x), (-1, 1)),
-                    search_word(input, &WORD, (y, x), (-1, 0)),
-                    search_word(input, &WORD, (y, x), (1, -1)),
-                    search_word(input, &WORD, (y, x), (1, 1)),
-                    search_word(input, &WORD, (y, x), (1, 0)),
-                    search_word(input, &WORD, (y, x), (0, 1)),
-                    search_word(input, &WORD, (y, x), (0, -1)),
-                ]
+        .flat_map(|(y, line)| (0..line.len()).map(move |x| (y, x)))
+        .flat_map(|start| {
+            words.iter().flat_map(move |(word, chars)| {
+                Direction::ALL
+                    .into_iter()
+                    .filter(move |direction| search_word(grid, chars, start, direction.offset()))
+                    .map(move |direction| Match {
+                        word,
+                        start,
+                        direction,
+                    })
             })
         })
-        .filter(|b| *b)
-        .count()
-        .to_string()
+        .collect()
+}
+
+#[aoc_generator(day4)]
+pub fn parse(input: &str) -> Vec<Vec<char>> {
+    input.lines().map(|line| line.chars().collect()).collect()
+}
+
+#[aoc(day4, part1)]
+pub fn part1(input: &[Vec<char>]) -> String {
+    find_words(input, &["XMAS"]).len().to_string()
 }
 
 #[aoc(day4, part2)]
-fn part2(input: &[Vec<char>]) -> String {
+pub fn part2(input: &[Vec<char>]) -> String {
     const WORD: [char; 3] = ['M', 'A', 'S'];
     input
         .iter()
@@ -69,6 +126,12 @@ fn part2(input: &[Vec<char>]) -> String {
         .to_string()
 }
 
+/// Summarizes the size of the grid.
+pub fn describe(input: &[Vec<char>]) -> String {
+    let columns = input.iter().map(|line| line.len()).max().unwrap_or(0);
+    format!("grid: {} rows, {} columns", input.len(), columns)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +147,79 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse(EXAMPLE)), "9");
     }
+
+    #[test]
+    fn find_words_in_small_grid() {
+        let grid = parse("CAT\nAXA\nTAC");
+        assert_eq!(
+            find_words(&grid, &["CAT", "", "XA"]),
+            [
+                Match {
+                    word: "CAT",
+                    start: (0, 0),
+                    direction: Direction::East
+                },
+                Match {
+                    word: "CAT",
+                    start: (0, 0),
+                    direction: Direction::South
+                },
+                Match {
+                    word: "XA",
+                    start: (1, 1),
+                    direction: Direction::North
+                },
+                Match {
+                    word: "XA",
+                    start: (1, 1),
+                    direction: Direction::East
+                },
+                Match {
+                    word: "XA",
+                    start: (1, 1),
+                    direction: Direction::South
+                },
+                Match {
+                    word: "XA",
+                    start: (1, 1),
+                    direction: Direction::West
+                },
+                Match {
+                    word: "CAT",
+                    start: (2, 2),
+                    direction: Direction::North
+                },
+                Match {
+                    word: "CAT",
+                    start: (2, 2),
+                    direction: Direction::West
+                },
+            ]
+        );
+        assert_eq!(find_words(&grid, &["X"]).len(), 8);
+        assert_eq!(find_words(&grid, &["CATS", "Q"]), []);
+    }
+
+    #[test]
+    fn find_reversed_words() {
+        let grid = parse(EXAMPLE);
+        let matches = find_words(&grid, &["XMAS", "SAMX"]);
+        let xmas = matches.iter().filter(|m| m.word == "XMAS").count();
+        assert_eq!(xmas, 18);
+        assert_eq!(matches.len(), 36);
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(describe(&parse(EXAMPLE)), "grid: 10 rows, 10 columns");
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day4", EXAMPLE, |input| {
+            let input = parse(input);
+            part1(&input);
+            part2(&input);
+        });
+    }
 }
@@ -4,38 +4,66 @@
 //! This assumption is not guaranteed by the AoC problem statement,
 //! but it is true for the input data.
 
+use std::collections::HashSet;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::{cache::Codec, error::ParseError};
+
 type PageNumber = usize;
 type PageOrderingRule = (PageNumber, PageNumber);
-struct Input {
+pub(crate) struct Input {
     ordering_rules: Vec<PageOrderingRule>,
     updates: Vec<Vec<PageNumber>>,
 }
 
+impl Codec for Input {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.ordering_rules.encode(out);
+        self.updates.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Input {
+            ordering_rules: Codec::decode(input)?,
+            updates: Codec::decode(input)?,
+        })
+    }
+}
+
 #[aoc_generator(day5)]
-fn parse(input: &str) -> Input {
-    let mut input = input.lines();
+pub(crate) fn parse(input: &str) -> Result<Input, ParseError> {
+    let mut input = input.lines().enumerate();
 
     let ordering_rules = input
         .by_ref()
-        .take_while(|line| !line.is_empty())
-        .map(|line| {
-            let (before, after) = line.split_once('|').unwrap();
-            let before = before.parse().unwrap();
-            let after = after.parse().unwrap();
-            (before, after)
+        .take_while(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            let not_a_number = |_| ParseError::new(i + 1, "not a page number");
+            let (before, after) = line
+                .split_once('|')
+                .ok_or_else(|| ParseError::new(i + 1, "missing delimiter '|'"))?;
+            let before = before.parse().map_err(not_a_number)?;
+            let after = after.parse().map_err(not_a_number)?;
+            Ok((before, after))
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     let updates = input
-        .map(|line| line.split(',').map(|page| page.parse().unwrap()).collect())
-        .collect();
+        .map(|(i, line)| {
+            line.split(',')
+                .map(|page| {
+                    page.parse()
+                        .map_err(|_| ParseError::new(i + 1, "not a page number"))
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()?;
 
-    Input {
+    Ok(Input {
         ordering_rules,
         updates,
-    }
+    })
 }
 
 fn is_update_ok(update: &[PageNumber], ordering_rules: &[PageOrderingRule]) -> bool {
@@ -45,18 +73,18 @@ fn is_update_ok(update: &[PageNumber], ordering_rules: &[PageOrderingRule]) -> b
 }
 
 #[aoc(day5, part1)]
-fn part1(input: &Input) -> String {
+pub(crate) fn part1(input: &Input) -> String {
     input
         .updates
         .iter()
         .filter(|update| is_update_ok(update, &input.ordering_rules))
-        .map(|update| update[update.len() / 2])
-        .sum::<usize>()
+        .map(|update| update[update.len() / 2] as u128)
+        .sum::<u128>()
         .to_string()
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &Input) -> String {
+pub(crate) fn part2(input: &Input) -> String {
     input
         .updates
         .iter()
@@ -75,24 +103,87 @@ fn part2(input: &Input) -> String {
                 fixed_update
             })
         })
-        .map(|update| update[update.len() / 2])
-        .sum::<usize>()
+        .map(|update| update[update.len() / 2] as u128)
+        .sum::<u128>()
         .to_string()
 }
 
+/// Summarizes the rules and updates and checks whether the assumption
+/// documented at the top of this module holds for the input.
+pub(crate) fn describe(input: &Input) -> String {
+    let ordering_rules = input.ordering_rules.iter().collect::<HashSet<_>>();
+    let is_totally_ordered = |update: &&Vec<PageNumber>| {
+        update.iter().enumerate().all(|(i, a)| {
+            update[i + 1..]
+                .iter()
+                .all(|b| ordering_rules.contains(&(*a, *b)) || ordering_rules.contains(&(*b, *a)))
+        })
+    };
+
+    let violations = input
+        .updates
+        .iter()
+        .filter(|update| !is_totally_ordered(update))
+        .count();
+    let assumption = if violations == 0 {
+        "holds".to_string()
+    } else {
+        format!("violated by {} updates", violations)
+    };
+    format!(
+        "ordering rules: {}\nupdates: {}\ntotal order assumption: {}",
+        input.ordering_rules.len(),
+        input.updates.len(),
+        assumption
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::{from_bytes, to_bytes};
 
     const EXAMPLE: &str = include_str!("../example/2024/day5.txt");
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), "143");
+        assert_eq!(part1(&parse(EXAMPLE).unwrap()), "143");
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), "123");
+        assert_eq!(part2(&parse(EXAMPLE).unwrap()), "123");
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE).unwrap()),
+            "ordering rules: 21\nupdates: 6\ntotal order assumption: holds"
+        );
+    }
+
+    #[test]
+    fn describe_violated_assumption() {
+        let input = parse("1|2\n2|3\n\n1,2\n1,2,3").unwrap();
+        assert!(describe(&input).ends_with("total order assumption: violated by 1 updates"));
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        let input = parse(EXAMPLE).unwrap();
+        let decoded = from_bytes(&to_bytes(&input)).unwrap();
+        assert_eq!(part1(&decoded), part1(&input));
+        assert_eq!(part2(&decoded), part2(&input));
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day5", EXAMPLE, |input| {
+            if let Ok(input) = parse(input) {
+                part1(&input);
+                part2(&input);
+            }
+        });
     }
 }
@@ -2,6 +2,12 @@ use std::{collections::HashSet, hash::Hash};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::{
+    budget::{Budget, Exhausted, TimedOut},
+    cache::Codec,
+    error::ParseError,
+};
+
 #[derive(Clone, Copy, Debug)]
 enum Tile {
     Empty,
@@ -10,7 +16,7 @@ enum Tile {
 }
 
 #[derive(Clone, Debug)]
-struct Map(Vec<Vec<Tile>>);
+pub(crate) struct Map(Vec<Vec<Tile>>);
 
 impl Map {
     fn get(&self, position: &(usize, usize)) -> Option<&Tile> {
@@ -21,6 +27,31 @@ impl Map {
     }
 }
 
+impl Codec for Tile {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(Tile::Empty),
+            1 => Some(Tile::Start),
+            2 => Some(Tile::Obstruction),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for Map {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Map(Codec::decode(input)?))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum Direction {
     Down,
@@ -49,8 +80,24 @@ impl Direction {
     }
 }
 
+impl Codec for Direction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(Direction::Down),
+            1 => Some(Direction::Left),
+            2 => Some(Direction::Right),
+            3 => Some(Direction::Up),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct Guard {
+pub(crate) struct Guard {
     position: (usize, usize),
     facing: Direction,
 }
@@ -71,22 +118,37 @@ impl Guard {
         }
         true
     }
-    fn is_loop(&mut self, map: &Map) -> bool {
+    fn is_loop(&mut self, map: &Map, budget: &Budget) -> Result<bool, Exhausted> {
         let mut states = HashSet::new();
         while self.tick(map) {
+            budget.check()?;
             if states.contains(self) {
-                return true;
+                return Ok(true);
             }
             states.insert(self.clone());
         }
-        false
+        Ok(false)
     }
 }
 
-type Input = (Map, Guard);
+impl Codec for Guard {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.position.encode(out);
+        self.facing.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Guard {
+            position: Codec::decode(input)?,
+            facing: Codec::decode(input)?,
+        })
+    }
+}
+
+pub(crate) type Input = (Map, Guard);
 
 #[aoc_generator(day6)]
-fn parse(input: &str) -> Input {
+pub(crate) fn parse(input: &str) -> Result<Input, ParseError> {
     let mut guard = None;
     let map = input
         .lines()
@@ -95,32 +157,39 @@ fn parse(input: &str) -> Input {
             line.chars()
                 .enumerate()
                 .map(|(x, c)| match c {
-                    '.' => Tile::Empty,
-                    '#' => Tile::Obstruction,
+                    '.' => Ok(Tile::Empty),
+                    '#' => Ok(Tile::Obstruction),
+                    '^' if guard.is_some() => Err(ParseError::new(y + 1, "more than one guard")),
                     '^' => {
                         guard = Some(Guard {
                             position: (y, x),
                             facing: Direction::Up,
                         });
-                        Tile::Start
+                        Ok(Tile::Start)
                     }
-                    c => panic!("unexpected character '{}'", c),
+                    c => Err(ParseError::new(
+                        y + 1,
+                        format!("unexpected character '{}'", c),
+                    )),
                 })
                 .collect()
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
-    (Map(map), guard.unwrap())
+    let guard = guard.ok_or_else(|| ParseError::input("missing guard"))?;
+    Ok((Map(map), guard))
 }
 
 #[aoc(day6, part1)]
-fn part1((map, guard): &Input) -> String {
+pub(crate) fn part1((map, guard): &Input) -> String {
     let mut guard = guard.clone();
     let mut visited_tiles = HashSet::new();
+    let mut states = HashSet::new();
 
     loop {
         visited_tiles.insert(guard.position);
-        if !guard.tick(map) {
+        // stop if the guard is stuck in a loop and will never leave the map
+        if !states.insert(guard.clone()) || !guard.tick(map) {
             break;
         }
     }
@@ -129,11 +198,21 @@ fn part1((map, guard): &Input) -> String {
 }
 
 #[aoc(day6, part2)]
-fn part2((map, guard_start): &Input) -> String {
+pub(crate) fn part2(input: &Input) -> String {
+    part2_with_budget(input, &Budget::unlimited()).expect("unlimited budget")
+}
+
+pub(crate) fn part2_with_budget(
+    (map, guard_start): &Input,
+    budget: &Budget,
+) -> Result<String, TimedOut> {
     let mut guard = guard_start.clone();
     let mut new_obstructions = HashSet::new();
+    let mut checked_positions = 0;
+    let mut states = HashSet::new();
 
-    while guard.tick(map) {
+    // stop if the guard is stuck in a loop even without a new obstruction
+    while guard.tick(map) && states.insert(guard.clone()) {
         let next_position = guard.next_position();
 
         match map.get(&next_position) {
@@ -153,27 +232,98 @@ fn part2((map, guard_start): &Input) -> String {
         // simulate new obstruction on next_position
         let mut map = map.clone();
         map.set(&next_position, Tile::Obstruction);
-        if guard_start.clone().is_loop(&map) {
-            new_obstructions.insert(next_position);
+        match guard_start.clone().is_loop(&map, budget) {
+            Ok(true) => {
+                new_obstructions.insert(next_position);
+            }
+            Ok(false) => (),
+            Err(Exhausted) => {
+                return Err(TimedOut {
+                    partial_answer: new_obstructions.len().to_string(),
+                    progress: format!(
+                        "checking {} positions on the guard's route",
+                        checked_positions
+                    ),
+                })
+            }
         }
+        checked_positions += 1;
     }
 
-    new_obstructions.len().to_string()
+    Ok(new_obstructions.len().to_string())
+}
+
+/// Summarizes the size of the map, the obstruction density and the guard's
+/// start position.
+pub(crate) fn describe((map, guard): &Input) -> String {
+    let rows = map.0.len();
+    let columns = map.0.iter().map(|row| row.len()).max().unwrap_or(0);
+    let tiles = map.0.iter().map(|row| row.len()).sum::<usize>();
+    let obstructions = map
+        .0
+        .iter()
+        .flatten()
+        .filter(|tile| matches!(tile, Tile::Obstruction))
+        .count();
+    let density = if tiles == 0 {
+        0.0
+    } else {
+        100.0 * obstructions as f64 / tiles as f64
+    };
+
+    format!(
+        "map: {} rows, {} columns\nobstructions: {} ({:.1}% of tiles)\nguard: row {}, column {}",
+        rows, columns, obstructions, density, guard.position.0, guard.position.1
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::{from_bytes, to_bytes};
 
     const EXAMPLE: &str = include_str!("../example/2024/day6.txt");
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), "41");
+        assert_eq!(part1(&parse(EXAMPLE).unwrap()), "41");
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), "6");
+        assert_eq!(part2(&parse(EXAMPLE).unwrap()), "6");
+    }
+
+    #[test]
+    fn part2_cancelled() {
+        let budget = Budget::unlimited();
+        budget.cancel();
+        assert!(part2_with_budget(&parse(EXAMPLE).unwrap(), &budget).is_err());
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE).unwrap()),
+            "map: 10 rows, 10 columns\nobstructions: 8 (8.0% of tiles)\nguard: row 6, column 4"
+        );
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        let input = parse(EXAMPLE).unwrap();
+        let decoded = from_bytes(&to_bytes(&input)).unwrap();
+        assert_eq!(part1(&decoded), part1(&input));
+        assert_eq!(part2(&decoded), part2(&input));
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day6", EXAMPLE, |input| {
+            if let Ok(input) = parse(input) {
+                part1(&input);
+                part2(&input);
+            }
+        });
     }
 }
@@ -1,106 +1,238 @@
+use std::collections::BTreeMap;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::{
+    budget::{Budget, Exhausted, TimedOut},
+    cache::Codec,
+    error::ParseError,
+};
+
 #[derive(Clone)]
-struct Equation {
+pub(crate) struct Equation {
     test_value: usize,
     numbers: Vec<usize>,
 }
 
 impl Equation {
-    fn is_possibly_true_p1(&self) -> bool {
-        self.is_possibly_true_recursive_p1(self.numbers[0], 1)
+    fn is_possibly_true_p1(&self, budget: &Budget) -> Result<bool, Exhausted> {
+        self.is_possibly_true_recursive_p1(self.numbers[0], 1, budget)
     }
-    fn is_possibly_true_recursive_p1(&self, current_value: usize, i: usize) -> bool {
+    fn is_possibly_true_recursive_p1(
+        &self,
+        current_value: usize,
+        i: usize,
+        budget: &Budget,
+    ) -> Result<bool, Exhausted> {
+        budget.check()?;
         if current_value > self.test_value {
-            false
+            Ok(false)
         } else if i == self.numbers.len() {
-            current_value == self.test_value
+            Ok(current_value == self.test_value)
         } else {
-            self.is_possibly_true_recursive_p1(current_value + self.numbers[i], i + 1)
-                || self.is_possibly_true_recursive_p1(current_value * self.numbers[i], i + 1)
+            // an overflowing value is always greater than the test value
+            let try_next = |v: Option<usize>| {
+                v.map_or(Ok(false), |v| {
+                    self.is_possibly_true_recursive_p1(v, i + 1, budget)
+                })
+            };
+            Ok(try_next(current_value.checked_add(self.numbers[i]))?
+                || try_next(current_value.checked_mul(self.numbers[i]))?)
         }
     }
 
-    fn is_possibly_true_p2(&self) -> bool {
-        self.is_possibly_true_recursive_p2(self.numbers[0], 1)
+    fn is_possibly_true_p2(&self, budget: &Budget) -> Result<bool, Exhausted> {
+        self.is_possibly_true_recursive_p2(self.numbers[0], 1, budget)
     }
-    fn is_possibly_true_recursive_p2(&self, current_value: usize, i: usize) -> bool {
+    fn is_possibly_true_recursive_p2(
+        &self,
+        current_value: usize,
+        i: usize,
+        budget: &Budget,
+    ) -> Result<bool, Exhausted> {
+        budget.check()?;
         if current_value > self.test_value {
-            false
+            Ok(false)
         } else if i == self.numbers.len() {
-            current_value == self.test_value
+            Ok(current_value == self.test_value)
         } else {
-            self.is_possibly_true_recursive_p2(current_value + self.numbers[i], i + 1)
-                || self.is_possibly_true_recursive_p2(current_value * self.numbers[i], i + 1)
-                || self.is_possibly_true_recursive_p2(
-                    concat_usize(current_value, self.numbers[i]),
-                    i + 1,
-                )
+            // an overflowing value is always greater than the test value
+            let try_next = |v: Option<usize>| {
+                v.map_or(Ok(false), |v| {
+                    self.is_possibly_true_recursive_p2(v, i + 1, budget)
+                })
+            };
+            Ok(try_next(current_value.checked_add(self.numbers[i]))?
+                || try_next(current_value.checked_mul(self.numbers[i]))?
+                || try_next(concat_usize(current_value, self.numbers[i]))?)
         }
     }
 }
 
-fn concat_usize(a: usize, b: usize) -> usize {
-    let mut factor = 10;
+impl Codec for Equation {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.test_value.encode(out);
+        self.numbers.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let test_value = Codec::decode(input)?;
+        let numbers = Vec::decode(input)?;
+        // the solvers rely on every equation having at least one number
+        (!numbers.is_empty()).then_some(Equation {
+            test_value,
+            numbers,
+        })
+    }
+}
+
+fn concat_usize(a: usize, b: usize) -> Option<usize> {
+    let mut factor: usize = 10;
     while factor <= b {
-        factor *= 10;
+        factor = factor.checked_mul(10)?;
     }
-    a * factor + b
+    a.checked_mul(factor)?.checked_add(b)
 }
 
 #[aoc_generator(day7)]
-fn parse(input: &str) -> Vec<Equation> {
+pub(crate) fn parse(input: &str) -> Result<Vec<Equation>, ParseError> {
     input
         .lines()
-        .map(|line| {
-            let (test_value, numbers) = line.split_once(':').unwrap();
-            let test_value = test_value.parse().unwrap();
+        .enumerate()
+        .map(|(i, line)| {
+            let not_a_number = |_| ParseError::new(i + 1, "not a number");
+            let (test_value, numbers) = line
+                .split_once(':')
+                .ok_or_else(|| ParseError::new(i + 1, "missing delimiter ':'"))?;
+            let test_value = test_value.parse().map_err(not_a_number)?;
             let numbers = numbers
                 .trim()
                 .split(' ')
-                .map(|v| v.parse().unwrap())
-                .collect();
-            Equation {
+                .map(|v| v.parse().map_err(not_a_number))
+                .collect::<Result<_, _>>()?;
+            Ok(Equation {
                 test_value,
                 numbers,
-            }
+            })
         })
         .collect()
 }
 
+/// Sums the test values of all equations which could possibly be true.
+fn sum_possibly_true(
+    input: &[Equation],
+    budget: &Budget,
+    is_possibly_true: fn(&Equation, &Budget) -> Result<bool, Exhausted>,
+) -> Result<String, TimedOut> {
+    // the sum of many test values need not fit into a usize
+    let mut sum = 0u128;
+    for (checked, equation) in input.iter().enumerate() {
+        match is_possibly_true(equation, budget) {
+            Ok(true) => sum += equation.test_value as u128,
+            Ok(false) => (),
+            Err(Exhausted) => {
+                return Err(TimedOut {
+                    partial_answer: sum.to_string(),
+                    progress: format!("checking {}/{} equations", checked, input.len()),
+                })
+            }
+        }
+    }
+    Ok(sum.to_string())
+}
+
 #[aoc(day7, part1)]
-fn part1(input: &[Equation]) -> String {
-    input
-        .iter()
-        .filter(|equation| equation.is_possibly_true_p1())
-        .map(|equation| equation.test_value)
-        .sum::<usize>()
-        .to_string()
+pub(crate) fn part1(input: &[Equation]) -> String {
+    part1_with_budget(input, &Budget::unlimited()).expect("unlimited budget")
+}
+
+pub(crate) fn part1_with_budget(input: &[Equation], budget: &Budget) -> Result<String, TimedOut> {
+    sum_possibly_true(input, budget, Equation::is_possibly_true_p1)
 }
 
 #[aoc(day7, part2)]
-fn part2(input: &[Equation]) -> String {
-    input
+pub(crate) fn part2(input: &[Equation]) -> String {
+    part2_with_budget(input, &Budget::unlimited()).expect("unlimited budget")
+}
+
+pub(crate) fn part2_with_budget(input: &[Equation], budget: &Budget) -> Result<String, TimedOut> {
+    sum_possibly_true(input, budget, Equation::is_possibly_true_p2)
+}
+
+/// Summarizes the number of equations and their operand counts, which
+/// determine the size of the search space.
+pub(crate) fn describe(input: &[Equation]) -> String {
+    let mut equations_by_operands = BTreeMap::<usize, usize>::new();
+    for equation in input {
+        *equations_by_operands
+            .entry(equation.numbers.len())
+            .or_default() += 1;
+    }
+
+    let operands = equations_by_operands
         .iter()
-        .filter(|equation| equation.is_possibly_true_p2())
-        .map(|equation| equation.test_value)
-        .sum::<usize>()
-        .to_string()
+        .map(|(operands, count)| format!("{} ({}x)", operands, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let max_test_value = input.iter().map(|e| e.test_value).max().unwrap_or(0);
+    format!(
+        "equations: {}\noperands: {}\nlargest test value: {}",
+        input.len(),
+        operands,
+        max_test_value
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::{from_bytes, to_bytes};
 
     const EXAMPLE: &str = include_str!("../example/2024/day7.txt");
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), "3749");
+        assert_eq!(part1(&parse(EXAMPLE).unwrap()), "3749");
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), "11387");
+        assert_eq!(part2(&parse(EXAMPLE).unwrap()), "11387");
+    }
+
+    #[test]
+    fn part2_cancelled() {
+        let input = parse(&format!("1000000: {}", ["1"; 30].join(" "))).unwrap();
+        let budget = Budget::unlimited();
+        budget.cancel();
+        let timed_out = part2_with_budget(&input, &budget).unwrap_err();
+        assert_eq!(timed_out.partial_answer, "0");
+        assert_eq!(timed_out.progress, "checking 0/1 equations");
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE).unwrap()),
+            "equations: 9\noperands: 2 (3x), 3 (3x), 4 (3x)\nlargest test value: 161011"
+        );
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        let input = parse(EXAMPLE).unwrap();
+        let decoded = from_bytes::<Vec<Equation>>(&to_bytes(&input)).unwrap();
+        assert_eq!(part1(&decoded), part1(&input));
+        assert_eq!(part2(&decoded), part2(&input));
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day7", EXAMPLE, |input| {
+            if let Ok(input) = parse(input) {
+                part1(&input);
+                part2(&input);
+            }
+        });
     }
 }
@@ -2,11 +2,13 @@ use std::collections::{HashMap, HashSet};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::cache::Codec;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Position(isize, isize);
 
 #[derive(Debug, Clone)]
-struct Map {
+pub(crate) struct Map {
     antennas_by_frequency: HashMap<char, Vec<Position>>,
     map_bounds: Position,
 }
@@ -17,8 +19,33 @@ impl Map {
     }
 }
 
+impl Codec for Position {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Position(Codec::decode(input)?, Codec::decode(input)?))
+    }
+}
+
+impl Codec for Map {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.antennas_by_frequency.encode(out);
+        self.map_bounds.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Map {
+            antennas_by_frequency: Codec::decode(input)?,
+            map_bounds: Codec::decode(input)?,
+        })
+    }
+}
+
 #[aoc_generator(day8)]
-fn parse(input: &str) -> Map {
+pub(crate) fn parse(input: &str) -> Map {
     let mut map_bounds = Position(0, 0);
     let mut antennas_by_frequency = HashMap::<char, Vec<_>>::new();
     for (y, line) in input.lines().enumerate() {
@@ -45,7 +72,7 @@ fn parse(input: &str) -> Map {
 }
 
 #[aoc(day8, part1)]
-fn part1(input: &Map) -> String {
+pub(crate) fn part1(input: &Map) -> String {
     let mut antinodes = HashSet::<Position>::new();
     for antennas in input.antennas_by_frequency.values() {
         for i in (0..antennas.len()).rev() {
@@ -67,7 +94,7 @@ fn part1(input: &Map) -> String {
 }
 
 #[aoc(day8, part2)]
-fn part2(input: &Map) -> String {
+pub(crate) fn part2(input: &Map) -> String {
     let mut antinodes = HashSet::<Position>::new();
     for antennas in input.antennas_by_frequency.values() {
         for i in (0..antennas.len()).rev() {
@@ -94,9 +121,26 @@ fn part2(input: &Map) -> String {
     antinodes.len().to_string()
 }
 
+/// Summarizes the size of the map and the antennas per frequency.
+pub(crate) fn describe(input: &Map) -> String {
+    let mut frequencies = input
+        .antennas_by_frequency
+        .iter()
+        .map(|(frequency, antennas)| format!("{} ({}x)", frequency, antennas.len()))
+        .collect::<Vec<_>>();
+    frequencies.sort();
+    format!(
+        "map: {} rows, {} columns\nfrequencies: {}",
+        input.map_bounds.0 + 1,
+        input.map_bounds.1 + 1,
+        frequencies.join(", ")
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::{from_bytes, to_bytes};
 
     const EXAMPLE: &str = include_str!("../example/2024/day8.txt");
 
@@ -109,4 +153,29 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse(EXAMPLE)), "34");
     }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE)),
+            "map: 12 rows, 12 columns\nfrequencies: 0 (4x), A (3x)"
+        );
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        let input = parse(EXAMPLE);
+        let decoded = from_bytes(&to_bytes(&input)).unwrap();
+        assert_eq!(part1(&decoded), part1(&input));
+        assert_eq!(part2(&decoded), part2(&input));
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day8", EXAMPLE, |input| {
+            let input = parse(input);
+            part1(&input);
+            part2(&input);
+        });
+    }
 }
@@ -2,7 +2,9 @@ use std::collections::HashSet;
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-type DiskMap = Vec<DiskSegment>;
+use crate::{cache::Codec, error::ParseError};
+
+pub(crate) type DiskMap = Vec<DiskSegment>;
 
 #[derive(Debug, Clone)]
 enum DiskBlock {
@@ -11,17 +13,52 @@ enum DiskBlock {
 }
 
 #[derive(Debug, Clone)]
-struct DiskSegment {
+pub(crate) struct DiskSegment {
     block_size: usize,
     block: DiskBlock,
 }
 
+impl Codec for DiskSegment {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.block_size.encode(out);
+        match self.block {
+            // file ids are shifted by one to make room for free blocks
+            DiskBlock::Free => 0,
+            DiskBlock::File(id) => id + 1,
+        }
+        .encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let block_size = usize::decode(input)?;
+        let block = match usize::decode(input)? {
+            0 => DiskBlock::Free,
+            id => DiskBlock::File(id - 1),
+        };
+        // the disk readers rely on segments never being empty
+        (block_size != 0).then_some(DiskSegment { block_size, block })
+    }
+}
+
 #[aoc_generator(day9)]
-fn parse(input: &str) -> DiskMap {
-    input
+pub(crate) fn parse(input: &str) -> Result<DiskMap, ParseError> {
+    let block_sizes = input
         .trim_end()
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as usize)
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| line.chars().map(move |c| (i, c)))
+        .map(|(i, c)| {
+            c.to_digit(10)
+                .map(|block_size| block_size as usize)
+                .ok_or_else(|| ParseError::new(i + 1, format!("unexpected character '{}'", c)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if input.trim_end().contains('\n') {
+        return Err(ParseError::input("the disk map must be a single line"));
+    }
+
+    Ok(block_sizes
+        .into_iter()
         .enumerate()
         .filter(|(_, block_size)| *block_size != 0)
         .map(|(i, block_size)| DiskSegment {
@@ -32,7 +69,7 @@ fn parse(input: &str) -> DiskMap {
                 DiskBlock::Free
             },
         })
-        .collect()
+        .collect())
 }
 
 struct DiskReader<'a> {
@@ -61,7 +98,7 @@ impl<'a> DiskReader<'a> {
         DiskReader {
             rev: true,
             segments,
-            segment_idx: segments.len() - 1,
+            segment_idx: segments.len().wrapping_sub(1),
             segment_buffer: None,
             block_idx,
             last_block_idx: block_idx,
@@ -142,7 +179,7 @@ impl<'a> DiskReader<'a> {
 }
 
 #[aoc(day9, part1)]
-fn part1(disk_map: &DiskMap) -> String {
+pub(crate) fn part1(disk_map: &DiskMap) -> String {
     let mut reader = DiskReader::new(disk_map);
     let mut reader_rev = DiskReader::new_rev(disk_map);
     let mut sum = 0;
@@ -167,7 +204,7 @@ fn part1(disk_map: &DiskMap) -> String {
 }
 
 #[aoc(day9, part2)]
-fn part2(disk_map: &DiskMap) -> String {
+pub(crate) fn part2(disk_map: &DiskMap) -> String {
     let mut reader = DiskReader::new(disk_map);
     let mut found_files = HashSet::new();
     let mut sum = 0;
@@ -200,19 +237,86 @@ fn part2(disk_map: &DiskMap) -> String {
     sum.to_string()
 }
 
+/// Summarizes the number of segments and blocks on the disk.
+pub(crate) fn describe(disk_map: &DiskMap) -> String {
+    let (mut files, mut file_blocks, mut free_blocks) = (0, 0, 0);
+    for segment in disk_map {
+        match segment.block {
+            DiskBlock::File(_) => {
+                files += 1;
+                file_blocks += segment.block_size;
+            }
+            DiskBlock::Free => free_blocks += segment.block_size,
+        }
+    }
+
+    format!(
+        "segments: {} ({} files, {} free)\nblocks: {} ({} file, {} free)",
+        disk_map.len(),
+        files,
+        disk_map.len() - files,
+        file_blocks + free_blocks,
+        file_blocks,
+        free_blocks
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::{from_bytes, to_bytes};
 
     const EXAMPLE: &str = include_str!("../example/2024/day9.txt");
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), "1928");
+        assert_eq!(part1(&parse(EXAMPLE).unwrap()), "1928");
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), "2858");
+        assert_eq!(part2(&parse(EXAMPLE).unwrap()), "2858");
+    }
+
+    #[test]
+    fn describe_example() {
+        assert_eq!(
+            describe(&parse(EXAMPLE).unwrap()),
+            "segments: 18 (10 files, 8 free)\nblocks: 42 (28 file, 14 free)"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_characters() {
+        assert_eq!(
+            parse("12x4").err(),
+            Some(ParseError::new(1, "unexpected character 'x'"))
+        );
+        assert_eq!(
+            parse("12\n34x").err(),
+            Some(ParseError::new(2, "unexpected character 'x'"))
+        );
+        assert_eq!(
+            parse("12\n34").err(),
+            Some(ParseError::input("the disk map must be a single line"))
+        );
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        let input = parse(EXAMPLE).unwrap();
+        let decoded = from_bytes::<DiskMap>(&to_bytes(&input)).unwrap();
+        assert_eq!(part1(&decoded), part1(&input));
+        assert_eq!(part2(&decoded), part2(&input));
+    }
+
+    #[test]
+    fn fuzz_parse_and_solve() {
+        crate::fuzz::run("day9", EXAMPLE, |input| {
+            if let Ok(input) = parse(input) {
+                part1(&input);
+                part2(&input);
+            }
+        });
     }
 }
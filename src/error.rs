@@ -0,0 +1,36 @@
+use std::{error::Error, fmt};
+
+/// Error returned by the generators if the puzzle input is malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line_number: Option<usize>,
+    message: String,
+}
+
+impl ParseError {
+    pub fn new(line_number: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line_number: Some(line_number),
+            message: message.into(),
+        }
+    }
+
+    /// Creates an error which cannot be attributed to a single line.
+    pub fn input(message: impl Into<String>) -> Self {
+        ParseError {
+            line_number: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line_number {
+            Some(line_number) => write!(f, "line {}: {}", line_number, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Error for ParseError {}
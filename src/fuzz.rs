@@ -0,0 +1,179 @@
+//! A small mutation fuzzer used by the tests of every day.
+//!
+//! Starting from the example input, random bit flips, line deletions and
+//! character insertions are applied and the mutated input is fed to the
+//! generator and both parts. Malformed input must be rejected with an error;
+//! a panic or a run exceeding [`TIMEOUT`] counts as a crash. Crashing inputs
+//! are saved to `fuzz/corpus/<name>/` and replayed on every subsequent run.
+//!
+//! The number of iterations and the seed can be overridden with the
+//! `AOC_FUZZ_ITERATIONS` and `AOC_FUZZ_SEED` environment variables.
+
+use std::{
+    any::Any,
+    env, fmt, fs,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use crate::cache::fnv1a;
+
+const DEFAULT_ITERATIONS: usize = 300;
+const MAX_MUTATIONS: usize = 4;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Characters which are likely to be meaningful to one of the parsers.
+const INTERESTING_CHARS: &[u8] = b"0123456789 \n,|:#.^()'-+mulontXMASA";
+
+/// xorshift64* generator, good enough to pick mutations reproducibly. Also
+/// used by tests which compare against a naive implementation.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift must not be seeded with zero
+        Rng(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Mutation {
+    BitFlip,
+    DeleteLine,
+    InsertChar,
+}
+
+impl Mutation {
+    const ALL: [Mutation; 3] = [Self::BitFlip, Self::DeleteLine, Self::InsertChar];
+
+    fn apply(self, input: &mut Vec<u8>, rng: &mut Rng) {
+        match self {
+            Self::BitFlip => {
+                if !input.is_empty() {
+                    let i = rng.below(input.len());
+                    input[i] ^= 1 << rng.below(8);
+                }
+            }
+            Self::DeleteLine => {
+                let mut lines = input.split(|c| *c == b'\n').collect::<Vec<_>>();
+                lines.remove(rng.below(lines.len()));
+                *input = lines.join(&b'\n');
+            }
+            Self::InsertChar => {
+                let c = if rng.below(4) == 0 {
+                    rng.next() as u8
+                } else {
+                    INTERESTING_CHARS[rng.below(INTERESTING_CHARS.len())]
+                };
+                input.insert(rng.below(input.len() + 1), c);
+            }
+        }
+    }
+}
+
+enum Crash {
+    Panic(String),
+    Timeout,
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Crash::Panic(message) => write!(f, "panicked with '{}'", message),
+            Crash::Timeout => write!(f, "timed out after {:?}", TIMEOUT),
+        }
+    }
+}
+
+/// Runs `target` on `input` in a separate thread, so that panics as well as
+/// endless loops can be detected.
+fn try_run(target: fn(&str), input: &str) -> Result<(), Crash> {
+    let (tx, rx) = mpsc::channel();
+    let input = input.to_string();
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| target(&input)));
+        // the receiver is gone if we already timed out
+        let _ = tx.send(result.map_err(panic_message));
+    });
+    match rx.recv_timeout(TIMEOUT) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(message)) => Err(Crash::Panic(message)),
+        // a hanging thread cannot be stopped, it is leaked until the process exits
+        Err(_) => Err(Crash::Timeout),
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<unknown panic payload>".to_string()
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Fuzzes `target` starting from `example`, panicking with a summary if any
+/// input crashed. `target` is expected to run the generator and, if it
+/// succeeds, both parts.
+pub fn run(name: &str, example: &str, target: fn(&str)) {
+    let corpus_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus")
+        .join(name);
+    let iterations = env_or("AOC_FUZZ_ITERATIONS", DEFAULT_ITERATIONS);
+    let mut rng = Rng::new(env_or("AOC_FUZZ_SEED", fnv1a(name.as_bytes())));
+
+    // replay previously found crashes before generating new inputs
+    let mut inputs = fs::read_dir(&corpus_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| fs::read(entry.ok()?.path()).ok())
+        .collect::<Vec<_>>();
+    inputs.extend((0..iterations).map(|_| {
+        let mut input = example.as_bytes().to_vec();
+        for _ in 0..=rng.below(MAX_MUTATIONS) {
+            Mutation::ALL[rng.below(Mutation::ALL.len())].apply(&mut input, &mut rng);
+        }
+        input
+    }));
+
+    let mut crashes = Vec::new();
+    for input in inputs {
+        let input = String::from_utf8_lossy(&input);
+        if let Err(crash) = try_run(target, &input) {
+            let path = corpus_dir.join(format!("{:016x}.txt", fnv1a(input.as_bytes())));
+            fs::create_dir_all(&corpus_dir).expect("failed to create corpus directory");
+            fs::write(&path, input.as_bytes()).expect("failed to write corpus entry");
+            crashes.push(format!("{}: {}", path.display(), crash));
+        }
+    }
+
+    assert!(
+        crashes.is_empty(),
+        "{} crashing input(s) for {}:\n{}",
+        crashes.len(),
+        name,
+        crashes.join("\n")
+    );
+}
@@ -1,10 +1,19 @@
-mod day1;
-mod day2;
-mod day3;
-mod day4;
+pub mod budget;
+mod cache;
+pub mod cli;
+mod client;
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day4;
 mod day5;
 mod day6;
 mod day7;
 mod day8;
 mod day9;
+pub mod error;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod mock_server;
 aoc_runner_derive::aoc_lib! { year = 2024 }
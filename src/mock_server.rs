@@ -0,0 +1,260 @@
+//! Local stand-in for the Advent of Code server, so that the client can be
+//! tested without touching the network.
+//!
+//! The server serves canned inputs and answers and emulates the responses to
+//! right, wrong and rate limited answers. Every connection is handled by a
+//! single background thread which lives until the test process exits.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+struct State {
+    session: String,
+    inputs: HashMap<u32, String>,
+    answers: HashMap<(u32, u32), String>,
+    solved: HashSet<(u32, u32)>,
+    cooldown: Duration,
+    locked_until: Option<Instant>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    cookie: Option<String>,
+    body: String,
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn new(status: u16, body: impl Into<String>) -> Self {
+        Response {
+            status,
+            body: body.into(),
+        }
+    }
+
+    fn article(text: &str) -> Self {
+        Self::new(
+            200,
+            format!(
+                "<html><main><article><p>{}</p></article></main></html>",
+                text
+            ),
+        )
+    }
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    /// Starts a server accepting only the given session token.
+    pub fn start(session: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        let address = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State {
+            session: session.to_string(),
+            inputs: HashMap::new(),
+            answers: HashMap::new(),
+            solved: HashSet::new(),
+            cooldown: Duration::from_secs(60),
+            locked_until: None,
+        }));
+
+        let server_state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // a broken connection only affects the test which opened it
+                let _ = handle_connection(stream, &server_state);
+            }
+        });
+
+        MockServer { address, state }
+    }
+
+    pub fn with_input(self, day: u32, input: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .inputs
+            .insert(day, input.to_string());
+        self
+    }
+
+    pub fn with_answer(self, day: u32, part: u32, answer: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .answers
+            .insert((day, part), answer.to_string());
+        self
+    }
+
+    /// Sets how long answers are refused after a wrong answer.
+    pub fn with_cooldown(self, cooldown: Duration) -> Self {
+        self.state.lock().unwrap().cooldown = cooldown;
+        self
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_request(&mut reader)?;
+    let response = respond(&request, &mut state.lock().unwrap());
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        if response.status == 200 { "OK" } else { "Error" },
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+
+    let mut cookie = None;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.to_ascii_lowercase().as_str() {
+                "cookie" => cookie = Some(value.trim().to_string()),
+                "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                _ => (),
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        cookie,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn respond(request: &Request, state: &mut State) -> Response {
+    let session = request
+        .cookie
+        .as_deref()
+        .and_then(|cookie| cookie.strip_prefix("session="));
+    if session != Some(state.session.as_str()) {
+        return Response::new(
+            400,
+            "Puzzle inputs differ by user.  Please log in to get your puzzle input.",
+        );
+    }
+
+    let route = request
+        .path
+        .strip_prefix("/2024/day/")
+        .and_then(|path| path.split_once('/'))
+        .and_then(|(day, rest)| Some((day.parse::<u32>().ok()?, rest)));
+    match (request.method.as_str(), route) {
+        ("GET", Some((day, "input"))) => match state.inputs.get(&day) {
+            Some(input) => Response::new(200, input.as_str()),
+            None => Response::new(404, "404 Not Found"),
+        },
+        ("POST", Some((day, "answer"))) => submit_answer(day, &request.body, state),
+        _ => Response::new(404, "404 Not Found"),
+    }
+}
+
+fn submit_answer(day: u32, form: &str, state: &mut State) -> Response {
+    let form = form
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key, decode_form_value(value)))
+        .collect::<HashMap<_, _>>();
+    let part = form.get("level").and_then(|level| level.parse().ok());
+    let (Some(part), Some(answer)) = (part, form.get("answer")) else {
+        return Response::new(400, "400 Bad Request");
+    };
+
+    let now = Instant::now();
+    if let Some(wait) = state
+        .locked_until
+        .and_then(|t| t.checked_duration_since(now))
+    {
+        return Response::article(&format!(
+            "You gave an answer too recently; you have to wait after submitting an answer before trying again.  You have {}s left to wait.",
+            wait.as_secs() + 1
+        ));
+    }
+
+    let expected = match state.answers.get(&(day, part)) {
+        Some(expected) if !state.solved.contains(&(day, part)) => expected,
+        _ => {
+            return Response::article(
+                "You don't seem to be solving the right level.  Did you already complete it?",
+            )
+        }
+    };
+    if answer == expected {
+        state.solved.insert((day, part));
+        return Response::article("That's the right answer!  You are one gold star closer.");
+    }
+
+    state.locked_until = Some(now + state.cooldown);
+    let hint = match (answer.parse::<i64>(), expected.parse::<i64>()) {
+        (Ok(answer), Ok(expected)) if answer > expected => "  your answer is too high.",
+        (Ok(answer), Ok(expected)) if answer < expected => "  your answer is too low.",
+        _ => "",
+    };
+    Response::article(&format!(
+        "That's not the right answer;{}  Please wait one minute before trying again.",
+        hint
+    ))
+}
+
+/// Decodes an `application/x-www-form-urlencoded` value.
+fn decode_form_value(value: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut input = value.bytes();
+    while let Some(b) = input.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next().unwrap_or(0), input.next().unwrap_or(0)];
+                let decoded = std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                bytes.push(decoded.unwrap_or(b'?'));
+            }
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}